@@ -1,4 +1,7 @@
-use std::{io::Read, os::fd::AsFd};
+use std::{
+    io::{Read, Seek, SeekFrom},
+    os::fd::AsFd,
+};
 
 use wayland_client::{
     Dispatch, QueueHandle,
@@ -11,6 +14,75 @@ use wayland_client::{
 
 use crate::error::Error;
 
+/// Buffer formats supported by [`crate::image::Image`] ordered by preference
+///
+/// the packed rgb formats are preferred since they don't need to be converted after the capture
+pub const PREFERRED_FORMATS: [Format; 4] = [Format::Bgr888, Format::Rgb888, Format::Xrgb8888, Format::Argb8888];
+
+/// get the amount of bytes a single pixel of a supported format needs
+pub fn bytes_per_pixel(format: Format) -> u32 {
+    match format {
+        Format::Bgr888 | Format::Rgb888 => 3,
+        _ => 4,
+    }
+}
+
+/// Buffer parameters offered by the compositor for a single frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct BufferOffer {
+    pub format: Format,
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+}
+
+/// choose the most preferred offer out of all buffers offered by the compositor
+///
+/// returns `None` when none of the offered formats are supported
+pub(crate) fn choose_offer(offers: &[BufferOffer]) -> Option<BufferOffer> {
+    offers
+        .iter()
+        .filter_map(|offer| PREFERRED_FORMATS.iter().position(|f| *f == offer.format).map(|rank| (rank, offer)))
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, offer)| *offer)
+}
+
+/// Caller provided buffer which a frame is copied into instead of allocating a new buffer
+#[derive(Debug, Clone)]
+pub(crate) struct BufferTarget {
+    pub buffer: WlBuffer,
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+    pub format: Format,
+    /// whether the compositor requested a buffer matching the target
+    pub accepted: bool,
+}
+
+impl BufferTarget {
+    /// check the buffers offered by the compositor against the target
+    ///
+    /// returns an error when none of the offered buffers match the target
+    pub fn accept(&mut self, offers: &[BufferOffer]) -> Result<(), Error> {
+        let matches = |offer: &BufferOffer| {
+            (self.width, self.height, self.stride, self.format) == (offer.width, offer.height, offer.stride, offer.format)
+        };
+        let accepted = offers.iter().any(matches);
+        self.accepted = accepted;
+        if self.accepted {
+            return Ok(());
+        }
+        Err(Error::BufferMismatch {
+            expected: format!("{}x{} (stride {}, {:?})", self.width, self.height, self.stride, self.format),
+            actual: offers
+                .iter()
+                .map(|offer| format!("{}x{} (stride {}, {:?})", offer.width, offer.height, offer.stride, offer.format))
+                .collect::<Vec<_>>()
+                .join(", "),
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct Buffer {
     pub buffer: WlBuffer,
@@ -36,8 +108,8 @@ impl Buffer {
         udata: K,
     ) -> Result<Self, Error> {
         let mfd = memfd::MemfdOptions::default().create("buffer").map_err(|err| Error::BufferCreate(err.into()))?;
-        mfd.as_file().set_len((width * height * 4) as u64).map_err(|err| Error::BufferCreate(err.into()))?;
-        let pool = shm.create_pool(mfd.as_file().as_fd(), (width * height * 4) as i32, handle, udata.clone());
+        mfd.as_file().set_len((stride * height) as u64).map_err(|err| Error::BufferCreate(err.into()))?;
+        let pool = shm.create_pool(mfd.as_file().as_fd(), (stride * height) as i32, handle, udata.clone());
         let buffer = pool.create_buffer(0, width as i32, height as i32, stride as i32, format, handle, udata);
 
         pool.destroy();
@@ -45,13 +117,29 @@ impl Buffer {
     }
 
     /// read the bytes from the temporary buffer file
+    ///
+    /// the file is read from the start, therefore the bytes can be read again after the buffer was reused for a capture
     pub fn get_bytes(&self) -> Result<Vec<u8>, Error> {
         // let mut file = unsafe { File::from_raw_fd(self.fd) };
         let mut bytes = Vec::new();
-        self.fd.as_file().read_to_end(&mut bytes).map_err(|err| Error::BufferRead(err))?;
+        let mut file = self.fd.as_file();
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_end(&mut bytes)?;
         Ok(bytes)
     }
 
+    /// get the parameters of the buffer which are compared against the buffer requested by the compositor
+    pub(crate) fn target(&self) -> BufferTarget {
+        BufferTarget {
+            buffer: self.buffer.clone(),
+            width: self.width,
+            height: self.height,
+            stride: self.stride,
+            format: self.format,
+            accepted: false,
+        }
+    }
+
     /// clear the wayland buffer and remove the temporary file
     ///
     /// should only be called after [`get_bytes`] since all data gets deleted by this function
@@ -1,20 +1,79 @@
 use wayland_backend::protocol::WEnumError;
-use wayland_client::DispatchError;
+use wayland_client::{DispatchError, backend::WaylandError, protocol::wl_shm::Format};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("the frame capture failed")]
     Failed,
+    #[error("the frame capture of {target} failed")]
+    CaptureFailed { target: String },
     #[error("no protocol object of type {0} was registered")]
     ProtocolNotAvailable(&'static str),
     #[error("unable to parse protocol enum: {0}")]
-    ProtocolInvalidEnum(WEnumError),
+    ProtocolInvalidEnum(#[from] WEnumError),
     #[error("error whilst dispatching: {0}")]
     WaylandDispatch(DispatchError),
+    #[error("the compositor sent protocol error {code} on {object}: {message}")]
+    Protocol { object: String, code: u32, message: String },
     #[error("tried to create buffer without having shm registered")]
     NoShm,
     #[error("unable to read buffer: {0}")]
-    BufferRead(std::io::Error),
+    BufferRead(#[from] std::io::Error),
     #[error("unable to create buffer: {0}")]
     BufferCreate(Box<dyn std::error::Error + Sync + Send>),
+    #[error("the provided buffer of {expected} does not match any of the offered buffers: {actual}")]
+    BufferMismatch { expected: String, actual: String },
+    #[error("no output with name {0} was found")]
+    OutputNotFound(String),
+    #[error("no monitor with id {0} was found")]
+    MonitorNotFound(String),
+    #[error("the buffer format {0:?} is not supported")]
+    UnsupportedFormat(Format),
+    #[error("none of the offered buffer formats {0:?} are supported")]
+    NoSupportedFormat(Vec<Format>),
+    #[error("unable to create image from buffer: {0}")]
+    ImageCreate(String),
+    #[error("expected {expected} bytes for a {width}x{height} image but got {actual}")]
+    ImageSize { width: u32, height: u32, expected: usize, actual: usize },
+    #[error("invalid window address: {0}")]
+    InvalidAddress(String),
+    #[error("invalid utf-8: {0}")]
+    InvalidUtf8(#[from] std::str::Utf8Error),
+    #[error("the compositor did not respond within {0:?}")]
+    Timeout(std::time::Duration),
+    #[error("the setup thread panicked before responding")]
+    SetupPanicked,
+    #[error("the region {0:?} does not overlap the output")]
+    EmptyRegion(crate::output::Region),
+}
+
+impl From<DispatchError> for Error {
+    /// protocol errors sent by the compositor are separated from other dispatch errors to surface their message
+    fn from(err: DispatchError) -> Self {
+        match err {
+            DispatchError::Backend(WaylandError::Protocol(err)) => Error::Protocol {
+                object: format!("{}@{}", err.object_interface, err.object_id),
+                code: err.code,
+                message: err.message,
+            },
+            err => Error::WaylandDispatch(err),
+        }
+    }
+}
+
+impl Error {
+    /// attach the capture target (e.g. the window or output) to an error of a capture which was failed by the compositor
+    ///
+    /// all other errors are returned unchanged
+    pub fn with_target(self, target: &str) -> Self {
+        match self {
+            Error::Failed => Error::CaptureFailed { target: target.to_string() },
+            err => err,
+        }
+    }
+
+    /// check whether the error was caused by a broken connection to the compositor
+    pub fn is_connection_lost(&self) -> bool {
+        matches!(self, Error::WaylandDispatch(DispatchError::Backend(WaylandError::Io(_))))
+    }
 }
@@ -1,19 +1,35 @@
 use wayland_client::{
-    Connection, Dispatch, delegate_noop,
+    Connection, Dispatch, EventQueue, Proxy, delegate_noop,
     protocol::{wl_buffer::WlBuffer, wl_registry, wl_shm::WlShm, wl_shm_pool::WlShmPool},
 };
 
 use crate::{
-    Frame,
-    buffer::Buffer,
+    Frame, FrameAction,
+    buffer::{Buffer, BufferOffer},
     error::Error,
+    image::Image,
     protocols::hyprland_toplevel_export_v1::{
         hyprland_toplevel_export_frame_v1::{self, HyprlandToplevelExportFrameV1},
         hyprland_toplevel_export_manager_v1::HyprlandToplevelExportManagerV1,
     },
+    toplevel::ToplevelHandle,
 };
 use std::sync::{Arc, Mutex, Weak};
 
+/// maximum number of frames which are requested at the same time by [`FrameManager::capture_frames`]
+const MAX_PIPELINED_CAPTURES: usize = 4;
+
+/// Window which should be captured by the [`FrameManager`]
+#[derive(Clone, Debug)]
+pub enum CaptureSource {
+    /// hyprland address of the window
+    Address(u64),
+    /// foreign toplevel handle of the window as advertised by the [`crate::toplevel::ToplevelManager`]
+    ///
+    /// **note**: capturing by handle requires version 2 of the `hyprland-toplevel-export-v1` protocol
+    Toplevel(ToplevelHandle),
+}
+
 #[derive(Clone)]
 pub struct FrameManager {
     shm: Option<WlShm>,
@@ -33,7 +49,7 @@ impl FrameManager {
 
         display.get_registry(&handle, ());
 
-        event_queue.roundtrip(&mut manager).map_err(|err| Error::WaylandDispatch(err))?;
+        event_queue.roundtrip(&mut manager)?;
 
         if let None = manager.manager {
             Err(Error::ProtocolNotAvailable(std::any::type_name::<HyprlandToplevelExportManagerV1>()))?
@@ -57,41 +73,217 @@ impl FrameManager {
         let mut event_queue = self.connection.new_event_queue();
         let handle = event_queue.handle();
         let hl_frame = hl_manager.capture_toplevel(0, window_handle as u32, &handle, Arc::downgrade(&frame));
+        self.finish_capture(frame, hl_frame, &mut event_queue)
+            .and_then(|frame| frame.buffer.ok_or(Error::Failed))
+            .map_err(|err| err.with_target(&format!("window {window_handle:#x}")))
+    }
+
+    /// capture a single frame buffer of multiple windows on a shared event queue
+    ///
+    /// up to [`MAX_PIPELINED_CAPTURES`] frames are requested at the same time and a new frame is requested as soon as
+    /// another one finished. a failing capture doesn't abort the remaining captures, the results are returned in the
+    /// order of the handles. when dispatching the shared queue fails, the dispatch error is returned for the first
+    /// unfinished handle and all other unfinished captures fail with [`Error::CaptureFailed`]
+    pub fn capture_frames(&self, handles: &[u64]) -> Vec<Result<Buffer, Error>> {
+        log::debug!("attempting to capture frames for {} windows", handles.len());
+
+        let Some(hl_manager) = self.manager.clone() else {
+            let protocol = std::any::type_name::<HyprlandToplevelExportManagerV1>();
+            return handles.iter().map(|_| Err(Error::ProtocolNotAvailable(protocol))).collect();
+        };
+
+        let mut manager = self.clone();
+        let mut event_queue = self.connection.new_event_queue();
+        let handle = event_queue.handle();
+        let mut results = handles.iter().map(|_| None).collect::<Vec<Option<Result<Buffer, Error>>>>();
+        let mut pending = handles.iter().copied().enumerate();
+        let mut active = Vec::new();
         loop {
-            if let Err(err) = event_queue.blocking_dispatch(self) {
-                Err(Error::WaylandDispatch(err))?;
+            while active.len() < MAX_PIPELINED_CAPTURES
+                && let Some((index, window_handle)) = pending.next()
+            {
+                let frame = Arc::new(Mutex::new(Frame::default()));
+                let hl_frame = hl_manager.capture_toplevel(0, window_handle as u32, &handle, Arc::downgrade(&frame));
+                active.push((index, frame, hl_frame));
+            }
+            if active.is_empty() {
+                break;
+            }
+
+            if let Err(err) = event_queue.blocking_dispatch(&mut manager) {
+                log::error!("unable to dispatch events of pipelined captures: {err}");
+                let mut err = Some(Error::from(err));
+                active.drain(..).for_each(|(_, frame, hl_frame)| {
+                    hl_frame.destroy();
+                    if let Some(buffer) = frame.lock().expect("lock should not be poisoned").buffer.take() {
+                        buffer.destroy();
+                    }
+                });
+                results.iter_mut().filter(|result| result.is_none()).for_each(|result| {
+                    *result = Some(Err(err.take().unwrap_or(Error::Failed)));
+                });
+                break;
             }
+
+            active.retain(|(index, frame, hl_frame)| {
+                let mut current = frame.lock().expect("lock should not be poisoned");
+                match current.poll() {
+                    FrameAction::Copy(buffer) => {
+                        hl_frame.copy(&buffer, 1);
+                        true
+                    }
+                    FrameAction::Continue => true,
+                    FrameAction::Destroy | FrameAction::Error => {
+                        hl_frame.destroy();
+                        let result = match current.error.take() {
+                            Some(err) => {
+                                if let Some(buffer) = current.buffer.take() {
+                                    buffer.destroy();
+                                }
+                                Err(err)
+                            }
+                            None => current.buffer.take().ok_or(Error::Failed),
+                        };
+                        results[*index] = Some(result);
+                        false
+                    }
+                }
+            });
+        }
+
+        results
+            .into_iter()
+            .zip(handles)
+            .map(|(result, window_handle)| {
+                result.unwrap_or(Err(Error::Failed)).map_err(|err| err.with_target(&format!("window {window_handle:#x}")))
+            })
+            .collect()
+    }
+
+    /// capture a single frame of a window into a buffer owned by the caller
+    ///
+    /// unlike [`FrameManager::capture_frame`] no new buffer is allocated, which allows reusing the same buffer for
+    /// continuous captures. returns an error when the compositor requests a buffer with other dimensions or format
+    pub fn capture_frame_into(&mut self, window_handle: u64, buffer: &mut Buffer) -> Result<(), Error> {
+        log::debug!("attempting to capture frame for window {window_handle} into provided buffer");
+
+        let Some(hl_manager) = &self.manager else {
+            Err(Error::ProtocolNotAvailable(std::any::type_name::<HyprlandToplevelExportManagerV1>()))?
+        };
+
+        let frame = Arc::new(Mutex::new(Frame { target: Some(buffer.target()), ..Default::default() }));
+        let mut event_queue = self.connection.new_event_queue();
+        let handle = event_queue.handle();
+        let hl_frame = hl_manager.capture_toplevel(0, window_handle as u32, &handle, Arc::downgrade(&frame));
+        self.finish_capture(frame, hl_frame, &mut event_queue)
+            .map(|_| ())
+            .map_err(|err| err.with_target(&format!("window {window_handle:#x}")))
+    }
+
+    /// capture a single frame buffer of a window by its foreign toplevel handle
+    pub fn capture_toplevel(&mut self, toplevel: &ToplevelHandle) -> Result<Buffer, Error> {
+        log::debug!("attempting to capture frame for foreign toplevel {}", toplevel.id());
+
+        let Some(hl_manager) = &self.manager else {
+            Err(Error::ProtocolNotAvailable(std::any::type_name::<HyprlandToplevelExportManagerV1>()))?
+        };
+        if hl_manager.version() < 2 {
+            Err(Error::ProtocolNotAvailable("hyprland_toplevel_export_manager_v1 version 2"))?
+        }
+
+        let frame = Arc::new(Mutex::new(Frame::default()));
+        let mut event_queue = self.connection.new_event_queue();
+        let handle = event_queue.handle();
+        let hl_frame = hl_manager.capture_toplevel_with_wlr_toplevel_handle(0, toplevel, &handle, Arc::downgrade(&frame));
+        self.finish_capture(frame, hl_frame, &mut event_queue)
+            .and_then(|frame| frame.buffer.ok_or(Error::Failed))
+            .map_err(|err| err.with_target(&format!("foreign toplevel {}", toplevel.id())))
+    }
+
+    /// capture a single frame buffer of a window using the given source
+    pub fn capture_source(&mut self, source: &CaptureSource) -> Result<Buffer, Error> {
+        match source {
+            CaptureSource::Address(window_handle) => self.capture_frame(*window_handle),
+            CaptureSource::Toplevel(toplevel) => self.capture_toplevel(toplevel),
+        }
+    }
+
+    /// dispatch the events of a requested frame until its buffer is ready or the capture failed
+    ///
+    /// the returned frame either holds the buffer or an accepted target
+    fn finish_capture(
+        &mut self,
+        frame: Arc<Mutex<Frame>>,
+        hl_frame: HyprlandToplevelExportFrameV1,
+        event_queue: &mut EventQueue<Self>,
+    ) -> Result<Frame, Error> {
+        loop {
+            event_queue.blocking_dispatch(self)?;
             let frame = frame.clone();
             let mut current = frame.lock().expect("lock should not be poisoned");
-            match (current.ready, current.requested, &current.error, &current.buffer) {
-                (_, _, Some(_), _) | (true, _, _, Some(_)) => {
+            match current.poll() {
+                FrameAction::Destroy | FrameAction::Error => {
                     hl_frame.destroy();
                     break;
                 }
-                (false, false, _, Some(buffer)) => {
-                    hl_frame.copy(&buffer.buffer, 1);
-                    current.requested = true;
-                }
-                _ => continue,
+                FrameAction::Copy(buffer) => hl_frame.copy(&buffer, 1),
+                FrameAction::Continue => continue,
             };
         }
 
         match Arc::into_inner(frame) {
             Some(frame) => {
-                let frame = frame.into_inner().expect("lock should not be poisoned");
-                if let Some(err) = frame.error {
-                    return Err(err);
-                }
-                if let Some(buffer) = frame.buffer {
-                    return Ok(buffer);
-                } else {
-                    unreachable!("we only exit the loop when buffer or error is some")
+                let mut frame = frame.into_inner().expect("lock should not be poisoned");
+                match frame.error.take() {
+                    Some(err) => Err(err),
+                    None => Ok(frame),
                 }
             }
             None => unreachable!("we only exit the loop after waiting blockingly for all dispatchers"),
         }
     }
 
+    /// capture a single frame of a window into an image without blocking the caller
+    ///
+    /// the returned future owns a clone of the manager and is `Send`, therefore it can be spawned on a multithreaded
+    /// runtime (e.g. `tokio::spawn`) and the resulting join handle can be awaited on the gtk main loop
+    /// (e.g. using `glib::spawn_future_local`)
+    ///
+    /// **note**: the capture itself is still blocking and occupies the thread which polls the future
+    pub fn capture_frame_image(&self, window_handle: u64) -> impl Future<Output = Result<Image, Error>> + Send + 'static {
+        let mut manager = self.clone();
+        async move {
+            let buffer = manager.capture_frame(window_handle)?;
+            Image::new(buffer).map_err(|err| Error::ImageCreate(err.to_string()))
+        }
+    }
+
+    /// capture a single frame of a window using the given source into an image without blocking the caller
+    ///
+    /// see [`FrameManager::capture_frame_image`] for how the returned future is meant to be used
+    pub fn capture_source_image(
+        &self,
+        source: CaptureSource,
+    ) -> impl Future<Output = Result<Image, Error>> + Send + 'static {
+        let mut manager = self.clone();
+        async move {
+            let buffer = manager.capture_source(&source)?;
+            Image::new(buffer).map_err(|err| Error::ImageCreate(err.to_string()))
+        }
+    }
+
+    /// capture a single frame buffer of a window by its hyprland address
+    ///
+    /// the address is expected to be a hex string as reported by hyprland (e.g. `0x5713073a6a20`)
+    pub fn capture_window_by_address(&mut self, address: &str) -> Result<Buffer, Error> {
+        let hex = address.trim().trim_start_matches("0x");
+        let window_handle = u64::from_str_radix(hex, 16).map_err(|_| Error::InvalidAddress(address.to_string()))?;
+        if window_handle == 0 {
+            return Err(Error::InvalidAddress(address.to_string()));
+        }
+        self.capture_frame(window_handle)
+    }
+
     /// destroy the internal objects of the frame manager
     pub fn destroy(&mut self) {
         if let Some(hl_manager) = &self.manager {
@@ -147,16 +339,9 @@ impl Dispatch<HyprlandToplevelExportFrameV1, Weak<Mutex<Frame>>> for FrameManage
             hyprland_toplevel_export_frame_v1::Event::Buffer { format, width, height, stride } => {
                 let format = match format.into_result() {
                     Ok(format) => format,
-                    Err(err) => return frame.error = Some(Error::ProtocolInvalidEnum(err)),
+                    Err(err) => return frame.error = Some(err.into()),
                 };
-                if let Some(shm) = &state.shm {
-                    match Buffer::new(shm, width, height, stride, format, qhandle, ()) {
-                        Ok(buffer) => frame.buffer = Some(buffer),
-                        Err(err) => frame.error = Some(err),
-                    }
-                } else {
-                    frame.error = Some(Error::ProtocolNotAvailable(std::any::type_name::<WlShm>()));
-                }
+                frame.offers.push(BufferOffer { format, width, height, stride });
             }
             hyprland_toplevel_export_frame_v1::Event::Damage { .. } => {}
             hyprland_toplevel_export_frame_v1::Event::Flags { .. } => {}
@@ -165,7 +350,7 @@ impl Dispatch<HyprlandToplevelExportFrameV1, Weak<Mutex<Frame>>> for FrameManage
             }
             hyprland_toplevel_export_frame_v1::Event::Failed => frame.error = Some(Error::Failed),
             hyprland_toplevel_export_frame_v1::Event::LinuxDmabuf { .. } => {}
-            hyprland_toplevel_export_frame_v1::Event::BufferDone => {}
+            hyprland_toplevel_export_frame_v1::Event::BufferDone => frame.finish_offers(state.shm.as_ref(), qhandle),
         }
     }
 }
@@ -1,9 +1,17 @@
 use image::{
-    RgbImage, RgbaImage,
-    imageops::{flip_vertical_in_place, resize, rotate90, rotate180_in_place, rotate270},
+    DynamicImage, ExtendedColorType, ImageEncoder, RgbImage, RgbaImage,
+    codecs::png::{CompressionType, FilterType as PngFilterType, PngEncoder},
+    imageops::{
+        FilterType, crop_imm, flip_horizontal_in_place, flip_vertical_in_place, resize, rotate90, rotate180_in_place,
+        rotate270,
+    },
 };
 
-use crate::buffer::Buffer;
+#[cfg(feature = "gtk")]
+use gtk4::gdk_pixbuf::{Colorspace, Pixbuf};
+use wayland_client::protocol::wl_shm::Format;
+
+use crate::{buffer::Buffer, error::Error};
 
 /// Xrgb8888 buffered image (as returned by hyprland) stored as a rgba image
 pub type XrgbImage = RgbaImage;
@@ -16,35 +24,137 @@ pub enum ImageKind {
 pub struct Image {
     pub buffer: ImageKind,
     pub aspect_ratio: f64,
+    /// the image was created from a buffer whose fourth channel is a (premultiplied) alpha channel
+    ///
+    /// the alpha channel is kept in the xrgb image until the image is converted to rgb
+    pub has_alpha: bool,
 }
 
+/// maximum number of samples per axis which are compared by [`Image::is_uniform`]
+const UNIFORM_SAMPLES: u32 = 64;
+
+/// gray values of the light and dark squares of the checkerboard drawn below transparent images
+const CHECKERBOARD_LIGHT: u8 = 204;
+const CHECKERBOARD_DARK: u8 = 153;
+
 impl Image {
     /// create a new image from a buffer storing a frame
+    ///
+    /// packed rgb buffers are stored as rgb image directly, all other formats are treated as xrgb. the alpha channel of
+    /// `Argb8888` buffers is preserved until the image is converted to rgb
     pub fn new(buffer: Buffer) -> Result<Self, Box<dyn std::error::Error>> {
         let bytes = buffer.get_bytes()?;
         buffer.destroy();
-        let img = match XrgbImage::from_vec(buffer.width, buffer.height, bytes) {
-            Some(img) => Self { buffer: ImageKind::Xrgb(img), aspect_ratio: buffer.width as f64 / buffer.height as f64 },
-            None => return Err(Box::from("failed to create xrgb image from buffer")),
+        let aspect_ratio = buffer.width as f64 / buffer.height as f64;
+        let img = match buffer.format {
+            Format::Bgr888 | Format::Rgb888 => {
+                let rgb = Self::unpad_rows(&bytes, buffer.width * 3, buffer.height, buffer.stride)?;
+                let Some(mut img) = RgbImage::from_vec(buffer.width, buffer.height, rgb) else {
+                    return Err(Box::from("failed to create rgb image from buffer"));
+                };
+                // the formats are little endian, therefore `Rgb888` buffers store the bytes in bgr order
+                if buffer.format == Format::Rgb888 {
+                    img.pixels_mut().for_each(|pixel| pixel.0.swap(0, 2));
+                }
+                Self { buffer: ImageKind::Rgb(img), aspect_ratio, has_alpha: false }
+            }
+            _ => match XrgbImage::from_vec(buffer.width, buffer.height, bytes) {
+                Some(img) => {
+                    Self { buffer: ImageKind::Xrgb(img), aspect_ratio, has_alpha: buffer.format == Format::Argb8888 }
+                }
+                None => return Err(Box::from("failed to create xrgb image from buffer")),
+            },
         };
         drop(buffer);
         Ok(img)
     }
 
+    /// create a new image from tightly packed rgb bytes
+    pub fn from_rgb(width: u32, height: u32, bytes: Vec<u8>) -> Result<Self, Error> {
+        Self::check_size(width, height, 3, bytes.len())?;
+        let buffer = RgbImage::from_vec(width, height, bytes).expect("the length of the bytes was checked");
+        Ok(Self { buffer: ImageKind::Rgb(buffer), aspect_ratio: width as f64 / height as f64, has_alpha: false })
+    }
+
+    /// create a new image from tightly packed rgba bytes
+    ///
+    /// the image is stored as xrgb image which keeps the alpha channel until it's converted to rgb. like the alpha
+    /// channel of wayland buffers, the alpha channel is expected to be premultiplied
+    pub fn from_rgba(width: u32, height: u32, mut bytes: Vec<u8>) -> Result<Self, Error> {
+        Self::check_size(width, height, 4, bytes.len())?;
+        // xrgb images store the channels of each pixel in little endian (bgra) order
+        bytes.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2));
+        let buffer = XrgbImage::from_vec(width, height, bytes).expect("the length of the bytes was checked");
+        Ok(Self { buffer: ImageKind::Xrgb(buffer), aspect_ratio: width as f64 / height as f64, has_alpha: true })
+    }
+
+    /// check that the byte length matches the dimensions of an image with `channels` bytes per pixel
+    fn check_size(width: u32, height: u32, channels: usize, actual: usize) -> Result<(), Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::ImageCreate(format!("the image dimensions {width}x{height} must be greater than zero")));
+        }
+        let expected = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(channels))
+            .ok_or_else(|| Error::ImageCreate(format!("the image dimensions {width}x{height} are too big")))?;
+        if expected != actual {
+            return Err(Error::ImageSize { width, height, expected, actual });
+        }
+        Ok(())
+    }
+
+    /// remove the padding at the end of every row of a buffer
+    fn unpad_rows(bytes: &[u8], row_len: u32, height: u32, stride: u32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        if stride < row_len || bytes.len() < (stride * height) as usize {
+            return Err(Box::from("buffer is smaller than the advertised dimensions"));
+        }
+        Ok(bytes.chunks(stride as usize).take(height as usize).flat_map(|row| &row[..row_len as usize]).copied().collect())
+    }
+
     /// resize the image buffer to the specified dimensions
     pub fn resize(&mut self, width: u32, height: u32) {
+        self.resize_with_filter(width, height, FilterType::Triangle);
+    }
+
+    /// resize the image buffer to the specified dimensions using the given filter
+    pub fn resize_with_filter(&mut self, width: u32, height: u32, filter: FilterType) {
         match &self.buffer {
             ImageKind::Rgb(image_buffer) => {
-                let sized = resize(image_buffer, width, height, image::imageops::FilterType::Triangle);
+                let sized = resize(image_buffer, width, height, filter);
                 self.buffer = ImageKind::Rgb(sized);
             }
             ImageKind::Xrgb(image_buffer) => {
-                let sized = resize(image_buffer, width, height, image::imageops::FilterType::Triangle);
+                let sized = resize(image_buffer, width, height, filter);
                 self.buffer = ImageKind::Xrgb(sized);
             }
         }
     }
 
+    /// rotate the image clockwise by `quarter_turns` times 90 degrees
+    pub fn rotate90(&mut self, quarter_turns: u8) {
+        match quarter_turns % 4 {
+            1 => {
+                self.buffer = match &self.buffer {
+                    ImageKind::Rgb(image_buffer) => ImageKind::Rgb(rotate90(image_buffer)),
+                    ImageKind::Xrgb(image_buffer) => ImageKind::Xrgb(rotate90(image_buffer)),
+                }
+            }
+            2 => match &mut self.buffer {
+                ImageKind::Rgb(image_buffer) => rotate180_in_place(image_buffer),
+                ImageKind::Xrgb(image_buffer) => rotate180_in_place(image_buffer),
+            },
+            3 => {
+                self.buffer = match &self.buffer {
+                    ImageKind::Rgb(image_buffer) => ImageKind::Rgb(rotate270(image_buffer)),
+                    ImageKind::Xrgb(image_buffer) => ImageKind::Xrgb(rotate270(image_buffer)),
+                }
+            }
+            _ => {}
+        }
+        let (width, height) = self.dimensions();
+        self.aspect_ratio = width as f64 / height as f64;
+    }
+
     /// apply an output transformation to the image
     pub fn transform(mut self, transform: Transforms) -> Self {
         self.buffer = match transform {
@@ -113,12 +223,201 @@ impl Image {
         self
     }
 
+    /// mirror the image along the vertical axis
+    pub fn flip_horizontal(&mut self) {
+        match &mut self.buffer {
+            ImageKind::Rgb(image_buffer) => flip_horizontal_in_place(image_buffer),
+            ImageKind::Xrgb(image_buffer) => flip_horizontal_in_place(image_buffer),
+        }
+    }
+
+    /// mirror the image along the horizontal axis
+    pub fn flip_vertical(&mut self) {
+        match &mut self.buffer {
+            ImageKind::Rgb(image_buffer) => flip_vertical_in_place(image_buffer),
+            ImageKind::Xrgb(image_buffer) => flip_vertical_in_place(image_buffer),
+        }
+    }
+
+    /// get the width and height of the image buffer
+    pub fn dimensions(&self) -> (u32, u32) {
+        match &self.buffer {
+            ImageKind::Rgb(image_buffer) => image_buffer.dimensions(),
+            ImageKind::Xrgb(image_buffer) => image_buffer.dimensions(),
+        }
+    }
+
+    /// get the average color of the image as rgb triplet
+    ///
+    /// every pixel is visited, therefore this should be called on the already downscaled image
+    pub fn average_color(&self) -> [u8; 3] {
+        let mut sum = [0u64; 3];
+        let mut pixels = 0u64;
+        match &self.buffer {
+            ImageKind::Rgb(image_buffer) => image_buffer.pixels().for_each(|pixel| {
+                sum.iter_mut().zip(pixel.0).for_each(|(sum, value)| *sum += value as u64);
+                pixels += 1;
+            }),
+            // xrgb pixels are stored as bgrx bytes
+            ImageKind::Xrgb(image_buffer) => image_buffer.pixels().for_each(|pixel| {
+                sum.iter_mut().zip(pixel.0.iter().take(3).rev()).for_each(|(sum, value)| *sum += *value as u64);
+                pixels += 1;
+            }),
+        }
+        if pixels == 0 {
+            return [0; 3];
+        }
+        sum.map(|sum| (sum / pixels) as u8)
+    }
+
+    /// check whether all pixels of the image have the same color (e.g. a window which wasn't rendered yet)
+    ///
+    /// the color channels of the pixels may differ from the first pixel by at most `tolerance`. the pixels are sampled
+    /// on a grid of at most [`UNIFORM_SAMPLES`] per axis, therefore large buffers can be checked without visiting
+    /// every pixel
+    pub fn is_uniform(&self, tolerance: u8) -> bool {
+        let (width, height) = self.dimensions();
+        if width == 0 || height == 0 {
+            return true;
+        }
+        let step_x = (width / UNIFORM_SAMPLES).max(1);
+        let step_y = (height / UNIFORM_SAMPLES).max(1);
+        // the alpha channel of xrgb pixels is ignored
+        let color = |x: u32, y: u32| -> [u8; 3] {
+            match &self.buffer {
+                ImageKind::Rgb(image_buffer) => image_buffer.get_pixel(x, y).0,
+                ImageKind::Xrgb(image_buffer) => {
+                    let [b, g, r, _] = image_buffer.get_pixel(x, y).0;
+                    [r, g, b]
+                }
+            }
+        };
+        let first = color(0, 0);
+        (0..height).step_by(step_y as usize).all(|y| {
+            (0..width)
+                .step_by(step_x as usize)
+                .all(|x| color(x, y).iter().zip(first).all(|(value, first)| value.abs_diff(first) <= tolerance))
+        })
+    }
+
+    /// get a hash of the dimensions and the rgb pixels of the image
+    ///
+    /// the hash uses 64-bit fnv-1a which is stable across runs and platforms. every pixel is visited, therefore this
+    /// should be called on the already downscaled image. the fourth byte of xrgb images is ignored
+    pub fn content_hash(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+        let hash_byte = |hash: u64, byte: u8| (hash ^ byte as u64).wrapping_mul(PRIME);
+
+        let (width, height) = self.dimensions();
+        let hash = width.to_le_bytes().into_iter().chain(height.to_le_bytes()).fold(OFFSET_BASIS, hash_byte);
+        match &self.buffer {
+            ImageKind::Rgb(image_buffer) => image_buffer.as_raw().iter().copied().fold(hash, hash_byte),
+            // xrgb pixels are stored as bgrx bytes
+            ImageKind::Xrgb(image_buffer) => {
+                image_buffer.pixels().flat_map(|pixel| pixel.0.into_iter().take(3).rev()).fold(hash, hash_byte)
+            }
+        }
+    }
+
+    /// alpha blend a rectangle filled with the rgba `color` onto the image
+    ///
+    /// the parts of the rectangle which are outside of the image are ignored
+    pub fn overlay_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: [u8; 4]) {
+        let (image_width, image_height) = self.dimensions();
+        let right = (x as i64 + width as i64).min(image_width as i64);
+        let bottom = (y as i64 + height as i64).min(image_height as i64);
+        for py in (y.max(0) as i64)..bottom {
+            for px in (x.max(0) as i64)..right {
+                self.blend_pixel(px as u32, py as u32, color);
+            }
+        }
+    }
+
+    /// alpha blend a rgba image onto the image with its top left corner at `x` and `y`
+    ///
+    /// the parts of the overlay which are outside of the image are ignored
+    pub fn overlay_image(&mut self, overlay: &RgbaImage, x: i32, y: i32) {
+        let (image_width, image_height) = self.dimensions();
+        overlay.enumerate_pixels().for_each(|(overlay_x, overlay_y, pixel)| {
+            let (px, py) = (x as i64 + overlay_x as i64, y as i64 + overlay_y as i64);
+            if px >= 0 && py >= 0 && px < image_width as i64 && py < image_height as i64 {
+                self.blend_pixel(px as u32, py as u32, pixel.0);
+            }
+        });
+    }
+
+    /// alpha blend a single rgba pixel onto the pixel at `x` and `y`
+    fn blend_pixel(&mut self, x: u32, y: u32, [r, g, b, a]: [u8; 4]) {
+        let blend = |dst: u8, src: u8| ((src as u32 * a as u32 + dst as u32 * (255 - a as u32)) / 255) as u8;
+        match &mut self.buffer {
+            ImageKind::Rgb(image_buffer) => {
+                let [dst_r, dst_g, dst_b] = image_buffer.get_pixel(x, y).0;
+                image_buffer.get_pixel_mut(x, y).0 = [blend(dst_r, r), blend(dst_g, g), blend(dst_b, b)];
+            }
+            // xrgb pixels are stored as bgrx bytes
+            ImageKind::Xrgb(image_buffer) => {
+                let [dst_b, dst_g, dst_r, dst_x] = image_buffer.get_pixel(x, y).0;
+                image_buffer.get_pixel_mut(x, y).0 = [blend(dst_b, b), blend(dst_g, g), blend(dst_r, r), dst_x];
+            }
+        }
+    }
+
+    /// composite the image onto a checkerboard of `square_size` pixel squares such that transparent regions are visible
+    ///
+    /// nothing happens unless the image has an alpha channel with at least one pixel which isn't fully opaque. afterwards
+    /// the image is opaque
+    pub fn composite_checkerboard(&mut self, square_size: u32) {
+        let ImageKind::Xrgb(image_buffer) = &mut self.buffer else {
+            return;
+        };
+        if !self.has_alpha || square_size == 0 || image_buffer.pixels().all(|pixel| pixel.0[3] == u8::MAX) {
+            return;
+        }
+        image_buffer.enumerate_pixels_mut().for_each(|(x, y, pixel)| {
+            let square = if (x / square_size + y / square_size) % 2 == 0 { CHECKERBOARD_LIGHT } else { CHECKERBOARD_DARK };
+            let [b, g, r, a] = pixel.0;
+            // the color channels are premultiplied, therefore only the checkerboard needs to be weighted
+            let blend = |src: u8| (src as u32 + square as u32 * (255 - a as u32) / 255).min(255) as u8;
+            pixel.0 = [blend(b), blend(g), blend(r), u8::MAX];
+        });
+        self.has_alpha = false;
+    }
+
+    /// crop the image buffer to the rectangle at `x` and `y` with the given dimensions
+    ///
+    /// the rectangle is clamped to the bounds of the image
+    pub fn crop(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        self.buffer = match &self.buffer {
+            ImageKind::Rgb(image_buffer) => ImageKind::Rgb(crop_imm(image_buffer, x, y, width, height).to_image()),
+            ImageKind::Xrgb(image_buffer) => ImageKind::Xrgb(crop_imm(image_buffer, x, y, width, height).to_image()),
+        };
+        let (width, height) = self.dimensions();
+        self.aspect_ratio = width as f64 / height as f64;
+    }
+
+    /// crop the center of the image buffer to the aspect ratio `target_ratio` (width / height)
+    ///
+    /// only the longer side relative to the target ratio is cropped, therefore the image is never upscaled. ratios
+    /// which aren't finite and positive are ignored
+    pub fn crop_to_aspect(&mut self, target_ratio: f64) {
+        if !target_ratio.is_finite() || target_ratio <= 0.0 {
+            return;
+        }
+        let (width, height) = self.dimensions();
+        let target_width = ((height as f64 * target_ratio).round() as u32).clamp(1, width);
+        let target_height = ((width as f64 / target_ratio).round() as u32).clamp(1, height);
+        if target_width < width {
+            self.crop((width - target_width) / 2, 0, target_width, height);
+        } else if target_height < height {
+            self.crop(0, (height - target_height) / 2, width, target_height);
+        }
+        self.aspect_ratio = target_ratio;
+    }
+
     /// resize the image buffer such that the bigger of the two dimensions is `size` long
     pub fn resize_to_fit(&mut self, size: u32) {
-        let (width, height) = match &self.buffer {
-            ImageKind::Rgb(image_buffer) => (image_buffer.width(), image_buffer.height()),
-            ImageKind::Xrgb(image_buffer) => (image_buffer.width(), image_buffer.height()),
-        };
+        let (width, height) = self.dimensions();
         if height > width && width > size {
             let height = (size as f64 / self.aspect_ratio) as u32;
             self.resize(size, height);
@@ -128,6 +427,44 @@ impl Image {
         }
     }
 
+    /// resize the image buffer such that the smaller of the two dimensions is `size` long
+    ///
+    /// unlike [`Image::resize_to_fit`] images which are smaller than `size` are upscaled as well
+    pub fn resize_to_fill(&mut self, size: u32) {
+        let (width, height) = self.dimensions();
+        if width.min(height) >= size {
+            return self.resize_to_fit(size);
+        }
+        if width > height {
+            let width = (size as f64 * self.aspect_ratio) as u32;
+            self.resize_with_filter(width, size, FilterType::CatmullRom);
+        } else {
+            let height = (size as f64 / self.aspect_ratio) as u32;
+            self.resize_with_filter(size, height, FilterType::CatmullRom);
+        }
+    }
+
+    /// encode the image as png
+    ///
+    /// the encoder doesn't embed any metadata (e.g. timestamps), therefore encoding the same image always produces the
+    /// same bytes which makes the output usable for content hashes
+    pub fn encode_png(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut bytes = Vec::new();
+        let encoder = PngEncoder::new_with_quality(&mut bytes, CompressionType::Default, PngFilterType::Adaptive);
+        match &self.buffer {
+            ImageKind::Rgb(image_buffer) => {
+                let (width, height) = image_buffer.dimensions();
+                encoder.write_image(image_buffer.as_raw(), width, height, ExtendedColorType::Rgb8)?;
+            }
+            ImageKind::Xrgb(image_buffer) => {
+                let rgb_buffer = Self::convert_xrgb_to_rgb(image_buffer.clone())?;
+                let (width, height) = rgb_buffer.dimensions();
+                encoder.write_image(rgb_buffer.as_raw(), width, height, ExtendedColorType::Rgb8)?;
+            }
+        }
+        Ok(bytes)
+    }
+
     /// convert a possible xrgb image instance into a rgb image instance
     ///
     /// if the instance is already a rgb instance nothing happens
@@ -137,7 +474,31 @@ impl Image {
         };
         let aspect_ratio = self.aspect_ratio;
 
-        Ok(Self { buffer: ImageKind::Rgb(Self::convert_xrgb_to_rgb(xrgb_buffer)?), aspect_ratio })
+        Ok(Self { buffer: ImageKind::Rgb(Self::convert_xrgb_to_rgb(xrgb_buffer)?), aspect_ratio, has_alpha: false })
+    }
+
+    /// turn the image into a [`DynamicImage`] of the `image` crate
+    ///
+    /// images with an alpha channel are returned as rgba image with straight (not premultiplied) alpha, all other
+    /// images are converted to rgb first
+    pub fn into_dynamic(self) -> Result<DynamicImage, Error> {
+        match self.buffer {
+            ImageKind::Xrgb(mut image_buffer) if self.has_alpha => {
+                image_buffer.pixels_mut().for_each(|pixel| {
+                    let [b, g, r, a] = pixel.0;
+                    let unpremultiply = |value: u8| match a {
+                        0 => 0,
+                        a => (value as u32 * 255 / a as u32).min(255) as u8,
+                    };
+                    pixel.0 = [unpremultiply(r), unpremultiply(g), unpremultiply(b), a];
+                });
+                Ok(DynamicImage::ImageRgba8(image_buffer))
+            }
+            _ => match self.into_rgb().map_err(|err| Error::ImageCreate(err.to_string()))?.buffer {
+                ImageKind::Rgb(image_buffer) => Ok(DynamicImage::ImageRgb8(image_buffer)),
+                ImageKind::Xrgb(_) => unreachable!("the image just got converted to rgb"),
+            },
+        }
     }
 
     /// convert a xrgb buffer into a rgb buffer
@@ -166,6 +527,24 @@ pub enum Transforms {
     Flipped270,
 }
 
+#[cfg(feature = "gtk")]
+impl Image {
+    /// turn the image into a gdk pixbuf which can directly be displayed inside a gtk picture
+    pub fn into_pixbuf(self) -> Result<Pixbuf, Error> {
+        let rgb_image = match self.into_rgb().map_err(|err| Error::ImageCreate(err.to_string()))?.buffer {
+            ImageKind::Xrgb(_) => unreachable!("the image just got converted to rgb"),
+            ImageKind::Rgb(image_buffer) => image_buffer,
+        };
+
+        let height = rgb_image.height() as i32;
+        let width = rgb_image.width() as i32;
+
+        let bytes = gtk4::glib::Bytes::from(&rgb_image.into_vec());
+        let pixbuf = Pixbuf::from_bytes(&bytes, Colorspace::Rgb, false, 8, width, height, width * 3);
+        Ok(pixbuf)
+    }
+}
+
 #[cfg(feature = "hyprland-rs")]
 impl From<hyprland::data::Transforms> for Transforms {
     fn from(value: hyprland::data::Transforms) -> Self {
@@ -181,3 +560,260 @@ impl From<hyprland::data::Transforms> for Transforms {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb_image(width: u32, height: u32, bytes: Vec<u8>) -> Image {
+        Image::from_rgb(width, height, bytes).expect("bytes should match dimensions")
+    }
+
+    fn rgb_bytes(image: &Image) -> Vec<u8> {
+        match &image.buffer {
+            ImageKind::Rgb(image_buffer) => image_buffer.as_raw().clone(),
+            ImageKind::Xrgb(image_buffer) => image_buffer.as_raw().clone(),
+        }
+    }
+
+    #[test]
+    fn flip_horizontal_swaps_columns() {
+        let mut image = rgb_image(2, 1, vec![1, 2, 3, 4, 5, 6]);
+        image.flip_horizontal();
+        assert_eq!(rgb_bytes(&image), vec![4, 5, 6, 1, 2, 3]);
+        image.flip_horizontal();
+        assert_eq!(rgb_bytes(&image), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn average_color_of_solid_image() {
+        let image = rgb_image(2, 2, [12, 34, 56].repeat(4));
+        assert_eq!(image.average_color(), [12, 34, 56]);
+    }
+
+    #[test]
+    fn average_color_of_xrgb_image() {
+        let buffer = XrgbImage::from_vec(2, 1, vec![56, 34, 12, 0, 58, 36, 14, 0]).expect("bytes should match dimensions");
+        let image = Image { buffer: ImageKind::Xrgb(buffer), aspect_ratio: 2.0, has_alpha: false };
+        assert_eq!(image.average_color(), [13, 35, 57]);
+    }
+
+    #[test]
+    fn overlay_rect_is_clipped_to_image() {
+        let mut image = rgb_image(2, 1, vec![0; 6]);
+        image.overlay_rect(1, -4, 10, 10, [255, 128, 0, 255]);
+        assert_eq!(rgb_bytes(&image), vec![0, 0, 0, 255, 128, 0]);
+        image.overlay_rect(5, 5, 1, 1, [255, 255, 255, 255]);
+        assert_eq!(rgb_bytes(&image), vec![0, 0, 0, 255, 128, 0]);
+    }
+
+    #[test]
+    fn rotate90_four_quarter_turns_is_identity() {
+        let mut image = rgb_image(2, 1, vec![1, 2, 3, 4, 5, 6]);
+        image.rotate90(1);
+        assert_eq!(image.dimensions(), (1, 2));
+        assert_eq!(image.aspect_ratio, 0.5);
+        (0..3).for_each(|_| image.rotate90(1));
+        assert_eq!(image.dimensions(), (2, 1));
+        assert_eq!(image.aspect_ratio, 2.0);
+        assert_eq!(rgb_bytes(&image), vec![1, 2, 3, 4, 5, 6]);
+        image.rotate90(4);
+        assert_eq!(rgb_bytes(&image), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn composite_checkerboard_fills_transparent_pixels() {
+        let buffer = XrgbImage::from_vec(2, 1, vec![0, 0, 0, 0, 10, 20, 30, 255]).expect("bytes should match dimensions");
+        let mut image = Image { buffer: ImageKind::Xrgb(buffer), aspect_ratio: 2.0, has_alpha: true };
+        image.composite_checkerboard(1);
+        assert_eq!(rgb_bytes(&image), vec![204, 204, 204, 255, 10, 20, 30, 255]);
+        assert!(!image.has_alpha);
+    }
+
+    #[test]
+    fn composite_checkerboard_ignores_opaque_images() {
+        let bytes = vec![0, 0, 0, 0, 10, 20, 30, 0];
+        let buffer = XrgbImage::from_vec(2, 1, bytes.clone()).expect("bytes should match dimensions");
+        // without alpha channel the fourth byte is padding and must not be treated as transparency
+        let mut image = Image { buffer: ImageKind::Xrgb(buffer), aspect_ratio: 2.0, has_alpha: false };
+        image.composite_checkerboard(1);
+        assert_eq!(rgb_bytes(&image), bytes);
+
+        let buffer = XrgbImage::from_vec(1, 1, vec![10, 20, 30, 255]).expect("bytes should match dimensions");
+        let mut image = Image { buffer: ImageKind::Xrgb(buffer), aspect_ratio: 1.0, has_alpha: true };
+        image.composite_checkerboard(1);
+        assert_eq!(rgb_bytes(&image), vec![10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn content_hash_matches_identical_pixels() {
+        let rgb = rgb_image(2, 1, vec![1, 2, 3, 4, 5, 6]);
+        let buffer = XrgbImage::from_vec(2, 1, vec![3, 2, 1, 0, 6, 5, 4, 255]).expect("bytes should match dimensions");
+        let xrgb = Image { buffer: ImageKind::Xrgb(buffer), aspect_ratio: 2.0, has_alpha: false };
+        assert_eq!(rgb.content_hash(), xrgb.content_hash());
+        assert_ne!(rgb.content_hash(), rgb_image(1, 2, vec![1, 2, 3, 4, 5, 6]).content_hash());
+        assert_ne!(rgb.content_hash(), rgb_image(2, 1, vec![1, 2, 3, 4, 5, 7]).content_hash());
+    }
+
+    #[test]
+    fn encode_png_is_deterministic() {
+        let image = rgb_image(2, 2, vec![12, 34, 56, 78, 90, 12, 34, 56, 78, 90, 12, 34]);
+        let first = image.encode_png().expect("image should be encodable");
+        let second = image.encode_png().expect("image should be encodable");
+        assert!(first.starts_with(b"\x89PNG"));
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn flip_vertical_keeps_single_row() {
+        let mut image = rgb_image(2, 1, vec![1, 2, 3, 4, 5, 6]);
+        image.flip_vertical();
+        assert_eq!(rgb_bytes(&image), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    /// 3x2 image whose pixels have the ids 1 to 6 in all channels (row by row)
+    fn asymmetric_image() -> Image {
+        rgb_image(3, 2, (1..=6).flat_map(|id| [id; 3]).collect())
+    }
+
+    /// get the ids of the pixels of an image built by [`asymmetric_image`] row by row
+    fn pixel_ids(image: &Image) -> Vec<u8> {
+        rgb_bytes(image).into_iter().step_by(3).collect()
+    }
+
+    #[test]
+    fn transform_produces_expected_orientation() {
+        let expected = [
+            (Transforms::Normal, (3, 2), vec![1, 2, 3, 4, 5, 6]),
+            (Transforms::Normal90, (2, 3), vec![4, 1, 5, 2, 6, 3]),
+            (Transforms::Normal180, (3, 2), vec![6, 5, 4, 3, 2, 1]),
+            (Transforms::Normal270, (2, 3), vec![3, 6, 2, 5, 1, 4]),
+            (Transforms::Flipped, (3, 2), vec![4, 5, 6, 1, 2, 3]),
+            (Transforms::Flipped90, (2, 3), vec![1, 4, 2, 5, 3, 6]),
+            (Transforms::Flipped180, (3, 2), vec![3, 2, 1, 6, 5, 4]),
+            (Transforms::Flipped270, (2, 3), vec![6, 3, 5, 2, 4, 1]),
+        ];
+        for (transform, dimensions, ids) in expected {
+            let image = asymmetric_image().transform(transform);
+            assert_eq!(image.dimensions(), dimensions);
+            assert_eq!(image.aspect_ratio, dimensions.0 as f64 / dimensions.1 as f64);
+            assert_eq!(pixel_ids(&image), ids);
+        }
+    }
+
+    #[test]
+    fn transform_round_trips() {
+        let image = asymmetric_image().transform(Transforms::Normal180).transform(Transforms::Normal180);
+        assert_eq!(pixel_ids(&image), pixel_ids(&asymmetric_image()));
+
+        let image = (0..4).fold(asymmetric_image(), |image, _| image.transform(Transforms::Normal90));
+        assert_eq!(image.dimensions(), (3, 2));
+        assert_eq!(pixel_ids(&image), pixel_ids(&asymmetric_image()));
+    }
+
+    #[test]
+    fn flip_composes_with_transform() {
+        // rotating by 90 degrees turns the 2x1 image into a 1x2 image with the first pixel at the top
+        let mut image = rgb_image(2, 1, vec![1, 2, 3, 4, 5, 6]).transform(Transforms::Normal90);
+        assert_eq!(rgb_bytes(&image), vec![1, 2, 3, 4, 5, 6]);
+        image.flip_vertical();
+        assert_eq!(rgb_bytes(&image), vec![4, 5, 6, 1, 2, 3]);
+        image.flip_horizontal();
+        assert_eq!(rgb_bytes(&image), vec![4, 5, 6, 1, 2, 3]);
+    }
+
+    #[test]
+    fn crop_to_aspect_crops_center_columns() {
+        let mut image = rgb_image(4, 1, (0..12).collect());
+        image.crop_to_aspect(2.0);
+        assert_eq!(image.dimensions(), (2, 1));
+        assert_eq!(rgb_bytes(&image), vec![3, 4, 5, 6, 7, 8]);
+        assert_eq!(image.aspect_ratio, 2.0);
+    }
+
+    #[test]
+    fn crop_to_aspect_crops_center_rows() {
+        let mut image = rgb_image(1, 3, (0..9).collect());
+        image.crop_to_aspect(1.0);
+        assert_eq!(image.dimensions(), (1, 1));
+        assert_eq!(rgb_bytes(&image), vec![3, 4, 5]);
+
+        let mut image = rgb_image(2, 2, vec![0; 12]);
+        image.crop_to_aspect(f64::NAN);
+        assert_eq!(image.dimensions(), (2, 2));
+    }
+
+    #[test]
+    fn resize_after_transform_keeps_rotated_aspect_ratio() {
+        let image = rgb_image(4, 2, vec![0; 24]);
+        let mut image = image.transform(Transforms::Normal90);
+        assert_eq!(image.dimensions(), (2, 4));
+        assert_eq!(image.aspect_ratio, 0.5);
+        image.resize_to_fit(1);
+        assert_eq!(image.dimensions(), (1, 2));
+        assert_eq!(image.aspect_ratio, 0.5);
+    }
+
+    #[test]
+    fn from_rgb_validates_length() {
+        let image = rgb_image(2, 1, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(image.aspect_ratio, 2.0);
+        assert!(matches!(
+            Image::from_rgb(2, 2, vec![0; 6]),
+            Err(Error::ImageSize { width: 2, height: 2, expected: 12, actual: 6 })
+        ));
+        assert!(matches!(Image::from_rgb(0, 2, Vec::new()), Err(Error::ImageCreate(_))));
+    }
+
+    #[test]
+    fn from_rgba_keeps_alpha_until_converted() {
+        let image = Image::from_rgba(1, 2, vec![1, 2, 3, 255, 4, 5, 6, 128]).expect("bytes should match dimensions");
+        assert!(image.has_alpha);
+        assert_eq!(image.aspect_ratio, 0.5);
+        let image = image.into_rgb().expect("image should be convertible");
+        assert_eq!(rgb_bytes(&image), vec![1, 2, 3, 4, 5, 6]);
+        assert!(Image::from_rgba(1, 1, vec![0; 3]).is_err());
+    }
+
+    #[test]
+    fn is_uniform_for_solid_image() {
+        let image = rgb_image(200, 100, [12, 34, 56].repeat(200 * 100));
+        assert!(image.is_uniform(0));
+
+        let buffer = XrgbImage::from_vec(2, 1, vec![0, 0, 0, 255, 1, 1, 1, 0]).expect("bytes should match dimensions");
+        let image = Image { buffer: ImageKind::Xrgb(buffer), aspect_ratio: 2.0, has_alpha: false };
+        assert!(!image.is_uniform(0));
+        assert!(image.is_uniform(1));
+    }
+
+    #[test]
+    fn is_uniform_for_noisy_image() {
+        let bytes = (0..200 * 100 * 3).map(|index| (index * 7 % 251) as u8).collect();
+        let image = rgb_image(200, 100, bytes);
+        assert!(!image.is_uniform(8));
+    }
+
+    #[test]
+    fn into_dynamic_converts_xrgb_to_rgb() {
+        let buffer = XrgbImage::from_vec(2, 1, vec![3, 2, 1, 0, 6, 5, 4, 0]).expect("bytes should match dimensions");
+        let image = Image { buffer: ImageKind::Xrgb(buffer), aspect_ratio: 2.0, has_alpha: false };
+        let DynamicImage::ImageRgb8(dynamic) = image.into_dynamic().expect("image should be convertible") else {
+            panic!("image without alpha should be converted to rgb");
+        };
+        assert_eq!(dynamic.dimensions(), (2, 1));
+        assert_eq!(dynamic.as_raw(), &vec![1, 2, 3, 4, 5, 6]);
+
+        let dynamic = rgb_image(1, 3, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]).into_dynamic().expect("image should be convertible");
+        assert_eq!(dynamic.as_rgb8().map(|buffer| buffer.dimensions()), Some((1, 3)));
+    }
+
+    #[test]
+    fn into_dynamic_keeps_alpha() {
+        let image = Image::from_rgba(2, 1, vec![10, 20, 30, 255, 50, 25, 0, 128]).expect("bytes should match dimensions");
+        let DynamicImage::ImageRgba8(dynamic) = image.into_dynamic().expect("image should be convertible") else {
+            panic!("image with alpha should be converted to rgba");
+        };
+        assert_eq!(dynamic.dimensions(), (2, 1));
+        assert_eq!(dynamic.as_raw(), &vec![10, 20, 30, 255, 99, 49, 0, 128]);
+    }
+}
@@ -6,10 +6,262 @@ pub mod output;
 mod protocols;
 pub mod toplevel;
 
+use std::{
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
+};
+
+use wayland_client::{
+    Connection, Dispatch, QueueHandle,
+    protocol::{wl_buffer::WlBuffer, wl_shm::WlShm, wl_shm_pool::WlShmPool},
+};
+
+use crate::{
+    buffer::{BufferOffer, PREFERRED_FORMATS},
+    error::Error,
+    image::Image,
+    output::OutputManager,
+};
+
 #[derive(Default)]
 struct Frame {
     pub ready: bool,
     pub requested: bool,
+    /// the compositor sent all buffer offers, the copy must not be requested before
+    pub buffer_done: bool,
+    /// all buffers offered by the compositor, one of them is chosen after all offers were sent
+    pub offers: Vec<BufferOffer>,
     pub buffer: Option<buffer::Buffer>,
+    /// caller provided buffer which the frame is copied into instead of [`Frame::buffer`]
+    pub target: Option<buffer::BufferTarget>,
     pub error: Option<error::Error>,
 }
+
+/// Next step of a capture loop as decided by [`Frame::poll`]
+#[derive(Debug, PartialEq)]
+enum FrameAction {
+    /// request the frame to be copied into the buffer
+    Copy(WlBuffer),
+    /// the frame is ready and the capture object can be destroyed
+    Destroy,
+    /// the capture failed and the capture object can be destroyed
+    Error,
+    /// wait for further events
+    Continue,
+}
+
+impl Frame {
+    /// choose one of the offered buffers once the compositor sent all offers
+    ///
+    /// a provided target is checked against all offers, otherwise a buffer of the most preferred format is allocated
+    fn finish_offers<T>(&mut self, shm: Option<&WlShm>, handle: &QueueHandle<T>)
+    where
+        T: Dispatch<WlBuffer, ()> + Dispatch<WlShmPool, ()> + Dispatch<WlShm, ()> + 'static,
+    {
+        self.buffer_done = true;
+        if let Some(target) = &mut self.target {
+            if let Err(err) = target.accept(&self.offers) {
+                self.error = Some(err);
+            }
+            return;
+        }
+        let Some(offer) = buffer::choose_offer(&self.offers) else {
+            let formats = self.offers.iter().map(|offer| offer.format).collect();
+            return self.error = Some(Error::NoSupportedFormat(formats));
+        };
+        log::debug!("chose buffer format {:?} out of {} offers", offer.format, self.offers.len());
+        let Some(shm) = shm else {
+            return self.error = Some(Error::ProtocolNotAvailable(std::any::type_name::<WlShm>()));
+        };
+        match buffer::Buffer::new(shm, offer.width, offer.height, offer.stride, offer.format, handle, ()) {
+            Ok(buffer) => self.buffer = Some(buffer),
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    /// decide the next step of the capture loop based on the current state
+    ///
+    /// the copy is only requested once the compositor sent all buffer offers and a provided target is only copied into
+    /// after the compositor requested a matching buffer. the frame is marked as requested when [`FrameAction::Copy`] is
+    /// returned
+    fn poll(&mut self) -> FrameAction {
+        let target = self.target.as_ref().filter(|target| target.accepted).map(|target| target.buffer.clone());
+        let buffer = self.buffer.as_ref().map(|buffer| buffer.buffer.clone()).or(target);
+        match (self.ready, self.requested, &self.error, buffer) {
+            (_, _, Some(_), _) => FrameAction::Error,
+            (true, _, _, Some(_)) => FrameAction::Destroy,
+            (false, false, _, Some(buffer)) if self.buffer_done => {
+                self.requested = true;
+                FrameAction::Copy(buffer)
+            }
+            _ => FrameAction::Continue,
+        }
+    }
+}
+
+/// Options for capturing an image using [`capture_output_image`]
+#[derive(Default, Debug, Clone)]
+pub struct CaptureOptions {
+    /// include the cursor in the captured image
+    pub overlay_cursor: bool,
+}
+
+/// Capture a single image of the output with the given name
+///
+/// this sets up a new output manager which is destroyed again after the capture
+pub fn capture_output_image(connection: &Connection, output_name: &str, opts: CaptureOptions) -> Result<Image, Error> {
+    let mut manager = OutputManager::new(connection)?;
+    let result = capture_output_image_with(&mut manager, output_name, &opts);
+    manager.destroy();
+    result
+}
+
+fn capture_output_image_with(manager: &mut OutputManager, output_name: &str, opts: &CaptureOptions) -> Result<Image, Error> {
+    let (wl_output, _) =
+        manager.find_output(output_name).cloned().ok_or_else(|| Error::OutputNotFound(output_name.to_string()))?;
+    let buffer = manager.capture_output_with_cursor(&wl_output, opts.overlay_cursor)?;
+    match buffer.format {
+        format if PREFERRED_FORMATS.contains(&format) => {
+            Image::new(buffer).map_err(|err| Error::ImageCreate(err.to_string()))
+        }
+        format => {
+            buffer.destroy();
+            Err(Error::UnsupportedFormat(format))
+        }
+    }
+}
+
+/// Run `f` on a separate thread and give up when it doesn't return within `timeout`
+///
+/// the thread is left behind when the timeout elapses
+pub(crate) fn with_timeout<T: Send + 'static>(
+    timeout: Duration,
+    f: impl FnOnce() -> Result<T, Error> + Send + 'static,
+) -> Result<T, Error> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(f());
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => Err(Error::Timeout(timeout)),
+        // the sender is only dropped without sending when `f` panicked
+        Err(RecvTimeoutError::Disconnected) => Err(Error::SetupPanicked),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixStream;
+
+    use wayland_client::{Proxy, protocol::wl_shm::Format};
+
+    use super::*;
+
+    fn target(accepted: bool) -> (Connection, buffer::BufferTarget) {
+        let (socket, _) = UnixStream::pair().expect("socket pair should be created");
+        let connection = Connection::from_socket(socket).expect("connection should be created");
+        let buffer = WlBuffer::inert(connection.backend().downgrade());
+        let target = buffer::BufferTarget { buffer, width: 4, height: 4, stride: 16, format: Format::Xrgb8888, accepted };
+        (connection, target)
+    }
+
+    #[test]
+    fn poll_waits_for_buffer() {
+        let mut frame = Frame::default();
+        assert_eq!(frame.poll(), FrameAction::Continue);
+        frame.ready = true;
+        assert_eq!(frame.poll(), FrameAction::Continue);
+        assert!(!frame.requested);
+    }
+
+    #[test]
+    fn poll_reports_error() {
+        let (_connection, target) = target(true);
+        let mut frame = Frame { error: Some(Error::Failed), ..Default::default() };
+        assert_eq!(frame.poll(), FrameAction::Error);
+        frame.target = Some(target);
+        frame.ready = true;
+        assert_eq!(frame.poll(), FrameAction::Error);
+        assert!(!frame.requested);
+    }
+
+    #[test]
+    fn poll_waits_for_buffer_done() {
+        let (_connection, target) = target(true);
+        let buffer = target.buffer.clone();
+        let mut frame = Frame { target: Some(target), ..Default::default() };
+        assert_eq!(frame.poll(), FrameAction::Continue);
+        assert!(!frame.requested);
+        frame.buffer_done = true;
+        assert_eq!(frame.poll(), FrameAction::Copy(buffer));
+    }
+
+    #[test]
+    fn poll_copies_once() {
+        let (_connection, target) = target(true);
+        let buffer = target.buffer.clone();
+        let mut frame = Frame { target: Some(target), buffer_done: true, ..Default::default() };
+        assert_eq!(frame.poll(), FrameAction::Copy(buffer));
+        assert!(frame.requested);
+        assert_eq!(frame.poll(), FrameAction::Continue);
+        frame.ready = true;
+        assert_eq!(frame.poll(), FrameAction::Destroy);
+    }
+
+    #[test]
+    fn poll_ignores_target_until_accepted() {
+        let (_connection, target) = target(false);
+        let mut frame = Frame { target: Some(target), buffer_done: true, ..Default::default() };
+        assert_eq!(frame.poll(), FrameAction::Continue);
+        frame.target.as_mut().expect("target should be set").accepted = true;
+        assert!(matches!(frame.poll(), FrameAction::Copy(_)));
+    }
+
+    fn offer(format: Format) -> BufferOffer {
+        BufferOffer { format, width: 4, height: 4, stride: 4 * buffer::bytes_per_pixel(format) }
+    }
+
+    #[test]
+    fn choose_offer_prefers_rgb() {
+        let offers = [offer(Format::Argb8888), offer(Format::Xrgb8888), offer(Format::Rgb888), offer(Format::Abgr8888)];
+        assert_eq!(buffer::choose_offer(&offers), Some(offer(Format::Rgb888)));
+        assert_eq!(buffer::choose_offer(&offers[..2]), Some(offer(Format::Xrgb8888)));
+    }
+
+    #[test]
+    fn choose_offer_rejects_unsupported_formats() {
+        assert_eq!(buffer::choose_offer(&[offer(Format::Abgr8888), offer(Format::Nv12)]), None);
+        assert_eq!(buffer::choose_offer(&[]), None);
+    }
+
+    #[test]
+    fn target_accepts_any_matching_offer() {
+        let (_connection, mut target) = target(false);
+        assert!(matches!(target.accept(&[offer(Format::Rgb888)]), Err(Error::BufferMismatch { .. })));
+        assert!(!target.accepted);
+        assert!(target.accept(&[offer(Format::Rgb888), offer(Format::Xrgb8888)]).is_ok());
+        assert!(target.accepted);
+    }
+
+    #[test]
+    fn with_timeout_returns_result() {
+        assert!(matches!(with_timeout(Duration::from_secs(5), || Ok(42)), Ok(42)));
+        assert!(matches!(with_timeout(Duration::from_secs(5), || Err::<(), _>(Error::NoShm)), Err(Error::NoShm)));
+    }
+
+    #[test]
+    fn with_timeout_gives_up() {
+        let result = with_timeout(Duration::from_millis(10), || {
+            std::thread::sleep(Duration::from_secs(1));
+            Ok(())
+        });
+        assert!(matches!(result, Err(Error::Timeout(_))));
+    }
+
+    #[test]
+    fn with_timeout_reports_panics() {
+        let result = with_timeout(Duration::from_secs(5), || -> Result<(), Error> { panic!("setup failed") });
+        assert!(matches!(result, Err(Error::SetupPanicked)));
+    }
+}
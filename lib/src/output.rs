@@ -1,21 +1,44 @@
-use std::sync::{Arc, Mutex, Weak};
+use std::{
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
 
 use wayland_client::{
-    Connection, Dispatch, EventQueue, delegate_noop,
+    Connection, Dispatch, EventQueue, Proxy, delegate_noop,
     protocol::{
         wl_buffer::WlBuffer,
         wl_output::{self, Mode, Subpixel, Transform, WlOutput},
         wl_registry,
-        wl_shm::WlShm,
+        wl_shm::{self, Format, WlShm},
         wl_shm_pool::WlShmPool,
     },
 };
+use wayland_protocols::ext::{
+    image_capture_source::v1::client::{
+        ext_image_capture_source_v1::ExtImageCaptureSourceV1,
+        ext_output_image_capture_source_manager_v1::ExtOutputImageCaptureSourceManagerV1,
+    },
+    image_copy_capture::v1::client::{
+        ext_image_copy_capture_frame_v1::{self, ExtImageCopyCaptureFrameV1},
+        ext_image_copy_capture_manager_v1::{ExtImageCopyCaptureManagerV1, Options},
+        ext_image_copy_capture_session_v1::{self, ExtImageCopyCaptureSessionV1},
+    },
+};
+use wayland_protocols::xdg::xdg_output::zv1::client::{
+    zxdg_output_manager_v1::ZxdgOutputManagerV1,
+    zxdg_output_v1::{self, ZxdgOutputV1},
+};
 use wayland_protocols_wlr::screencopy::v1::client::{
     zwlr_screencopy_frame_v1::{self, ZwlrScreencopyFrameV1},
     zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1,
 };
 
-use crate::{Frame, buffer::Buffer, error::Error};
+use crate::{
+    Frame, FrameAction,
+    buffer::{Buffer, BufferOffer, PREFERRED_FORMATS, bytes_per_pixel},
+    error::Error,
+    image::Image,
+};
 
 #[derive(Debug, Clone)]
 pub struct Geometry {
@@ -34,22 +57,161 @@ pub struct OutputMode {
     pub mode: Mode,
     pub width: i32,
     pub height: i32,
+    /// refresh rate of the mode in mHz
     pub refresh: i32,
 }
 
+impl std::fmt::Display for OutputMode {
+    /// format the mode as `<width>x<height>@<refresh>Hz` (e.g. `2560x1440@143.86Hz`)
+    ///
+    /// the refresh rate is rounded to two decimals and omitted when the compositor didn't advertise it
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)?;
+        if self.refresh <= 0 {
+            return Ok(());
+        }
+        let refresh = format!("{:.2}", self.refresh as f64 / 1000.0);
+        write!(f, "@{}Hz", refresh.trim_end_matches('0').trim_end_matches('.'))
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct Output {
     pub name: Option<String>,
     pub description: Option<String>,
-    pub scale: Option<i32>,
+    /// scale factor of the output
+    ///
+    /// fractional scales are derived from the logical size advertised by `xdg-output`, compositors without the protocol
+    /// only provide the integer scale of the `wl_output`
+    pub scale: Option<f64>,
     pub mode: Option<OutputMode>,
     pub geometry: Option<Geometry>,
+    /// size of the output in the global compositor space as advertised by `xdg-output`
+    pub logical_size: Option<(i32, i32)>,
+}
+
+impl Output {
+    /// get the dimensions of the current mode with the output transform applied
+    ///
+    /// returns `None` when the output didn't advertise a mode
+    pub fn transformed_size(&self) -> Option<(i32, i32)> {
+        let mode = self.mode.as_ref()?;
+        let rotated = self.geometry.as_ref().is_some_and(|geometry| {
+            matches!(geometry.transform, Transform::_90 | Transform::_270 | Transform::Flipped90 | Transform::Flipped270)
+        });
+        Some(if rotated { (mode.height, mode.width) } else { (mode.width, mode.height) })
+    }
+
+    /// get the fractional scale of the output from the ratio between the transformed mode and the logical size
+    ///
+    /// returns `None` when either the mode or the logical size is unknown
+    pub fn logical_scale(&self) -> Option<f64> {
+        let (width, _) = self.transformed_size()?;
+        let (logical_width, _) = self.logical_size?;
+        if logical_width <= 0 {
+            return None;
+        }
+        Some(width as f64 / logical_width as f64)
+    }
+
+    /// get the physical width and height of the output in millimeters
+    ///
+    /// returns `None` when the output didn't advertise its geometry or has no physical size (e.g. projectors)
+    pub fn physical_size_mm(&self) -> Option<(i32, i32)> {
+        let geometry = self.geometry.as_ref()?;
+        if geometry.physical_width <= 0 || geometry.physical_height <= 0 {
+            return None;
+        }
+        Some((geometry.physical_width, geometry.physical_height))
+    }
+
+    /// get the diagonal dots per inch of the output derived from the current mode and the physical size
+    ///
+    /// returns `None` when either the mode or the physical size is unknown
+    pub fn dpi(&self) -> Option<f64> {
+        let mode = self.mode.as_ref()?;
+        let (width_mm, height_mm) = self.physical_size_mm()?;
+        let diagonal_px = (mode.width as f64).hypot(mode.height as f64);
+        let diagonal_in = (width_mm as f64).hypot(height_mm as f64) / 25.4;
+        Some(diagonal_px / diagonal_in)
+    }
+}
+
+/// Rectangle on an output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Region {
+    /// clamp the region to a `width` x `height` rectangle at the origin
+    ///
+    /// returns `None` when the clamped region has no area
+    pub fn clamp(&self, width: i32, height: i32) -> Option<Self> {
+        let x = self.x.clamp(0, width);
+        let y = self.y.clamp(0, height);
+        let right = self.x.saturating_add(self.width).clamp(0, width);
+        let bottom = self.y.saturating_add(self.height).clamp(0, height);
+        if right <= x || bottom <= y {
+            return None;
+        }
+        Some(Self { x, y, width: right - x, height: bottom - y })
+    }
+}
+
+/// Buffer constraints advertised by an `ext-image-copy-capture-v1` session
+#[derive(Default)]
+struct Session {
+    pub width: u32,
+    pub height: u32,
+    pub formats: Vec<Format>,
+    pub done: bool,
+    pub stopped: bool,
+}
+
+/// find the output with the given name
+///
+/// when multiple outputs share the same name they're disambiguated using the description and afterwards the make and model
+/// of the monitor. if neither matches, the first output with the name is returned
+pub fn find_named_output<'a, T>(
+    outputs: &'a [(T, Output)],
+    name: &str,
+    description: &str,
+    make: &str,
+    model: &str,
+) -> Option<&'a (T, Output)> {
+    let candidates = outputs.iter().filter(|(_, output)| output.name.as_deref() == Some(name)).collect::<Vec<_>>();
+    if candidates.len() <= 1 {
+        return candidates.first().copied();
+    }
+
+    log::warn!("found {} outputs with name {name}: disambiguating by description", candidates.len());
+    candidates
+        .iter()
+        .find(|(_, output)| output.description.as_deref() == Some(description))
+        .or_else(|| {
+            candidates.iter().find(|(_, output)| {
+                output.geometry.as_ref().is_some_and(|geometry| geometry.make == make && geometry.model == model)
+            })
+        })
+        .or(candidates.first())
+        .copied()
 }
 
+/// Timeout of the setup of an output manager created with [`OutputManager::new`]
+pub const DEFAULT_SETUP_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[derive(Clone)]
 pub struct OutputManager {
     shm: Option<WlShm>,
     manager: Option<ZwlrScreencopyManagerV1>,
+    copy_manager: Option<ExtImageCopyCaptureManagerV1>,
+    source_manager: Option<ExtOutputImageCaptureSourceManagerV1>,
+    xdg_output_manager: Option<ZxdgOutputManagerV1>,
+    formats: Vec<Format>,
     pub outputs: Vec<(WlOutput, Output)>,
     intialized_outputs: u32,
     connection: Connection,
@@ -57,33 +219,85 @@ pub struct OutputManager {
 
 impl OutputManager {
     /// setup a new output manager which can be used to capture one or more frames of outputs or of selected regions
+    ///
+    /// gives up after [`DEFAULT_SETUP_TIMEOUT`] when the compositor doesn't answer the roundtrips
     pub fn new(connection: &Connection) -> Result<Self, Error> {
+        Self::new_with_timeout(connection, DEFAULT_SETUP_TIMEOUT)
+    }
+
+    /// setup a new output manager like [`OutputManager::new`] but give up when the compositor doesn't answer the
+    /// roundtrips within `timeout`
+    pub fn new_with_timeout(connection: &Connection, timeout: Duration) -> Result<Self, Error> {
+        let connection = connection.clone();
+        crate::with_timeout(timeout, move || Self::setup(&connection))
+    }
+
+    /// bind the globals and wait for the output information
+    fn setup(connection: &Connection) -> Result<Self, Error> {
         let display = connection.display();
 
         let mut event_queue = connection.new_event_queue();
         let handle = event_queue.handle();
 
-        let mut manager =
-            Self { shm: None, manager: None, outputs: Vec::new(), intialized_outputs: 0, connection: connection.clone() };
+        let mut manager = Self {
+            shm: None,
+            manager: None,
+            copy_manager: None,
+            source_manager: None,
+            xdg_output_manager: None,
+            formats: Vec::new(),
+            outputs: Vec::new(),
+            intialized_outputs: 0,
+            connection: connection.clone(),
+        };
 
         display.get_registry(&handle, ());
 
-        event_queue.roundtrip(&mut manager).map_err(|err| Error::WaylandDispatch(err))?;
+        event_queue.roundtrip(&mut manager)?;
 
-        if let None = manager.manager {
+        if manager.has_ext_capture() {
+            log::info!("using ext-image-copy-capture-v1 protocol for output captures");
+        } else if manager.manager.is_some() {
+            log::info!("using wlr-screencopy-unstable-v1 protocol for output captures");
+        } else {
             Err(Error::ProtocolNotAvailable(std::any::type_name::<ZwlrScreencopyManagerV1>()))?
         }
         if let None = manager.shm {
             Err(Error::ProtocolNotAvailable(std::any::type_name::<WlShm>()))?
         }
 
-        event_queue.roundtrip(&mut manager).map_err(|err| Error::WaylandDispatch(err))?;
+        if let Some(xdg_output_manager) = &manager.xdg_output_manager {
+            manager.outputs.iter().for_each(|(wl_output, _)| {
+                xdg_output_manager.get_xdg_output(wl_output, &handle, wl_output.clone());
+            });
+        }
+
+        // the second roundtrip waits for the output information and the formats advertised by the shm global
+        event_queue.roundtrip(&mut manager)?;
+
+        // the integer scale of the wl_output is only kept when the fractional scale is unknown
+        manager.outputs.iter_mut().for_each(|(_, output)| {
+            if let Some(scale) = output.logical_scale() {
+                output.scale = Some(scale);
+            }
+        });
 
         Ok(manager)
     }
 
     /// capture a single frame buffer of an output
     pub fn capture_output(&mut self, output: &WlOutput) -> Result<Buffer, Error> {
+        self.capture_output_with_cursor(output, false)
+    }
+
+    /// capture a single frame buffer of an output optionally including the cursor
+    ///
+    /// the `ext-image-copy-capture-v1` protocol is preferred over `wlr-screencopy-unstable-v1` when advertised
+    pub fn capture_output_with_cursor(&mut self, output: &WlOutput, overlay_cursor: bool) -> Result<Buffer, Error> {
+        if self.has_ext_capture() {
+            return self.capture_output_ext(output, overlay_cursor).map_err(|err| err.with_target(&self.target(output)));
+        }
+
         let Some(zwlr_manager) = &self.manager else {
             Err(Error::ProtocolNotAvailable(std::any::type_name::<ZwlrScreencopyManagerV1>()))?
         };
@@ -91,11 +305,176 @@ impl OutputManager {
         let frame = Arc::new(Mutex::new(Frame::default()));
         let mut event_queue = self.connection.new_event_queue();
         let handle = event_queue.handle();
-        let zwlr_frame = zwlr_manager.capture_output(0, output, &handle, Arc::downgrade(&frame));
-        self.finish_capture(frame, zwlr_frame, &mut event_queue)
+        let zwlr_frame = zwlr_manager.capture_output(overlay_cursor as i32, output, &handle, Arc::downgrade(&frame));
+        self.finish_capture(frame, zwlr_frame, &mut event_queue).map_err(|err| err.with_target(&self.target(output)))
+    }
+
+    /// capture a single frame of an output into an image without blocking the caller
+    ///
+    /// see [`crate::frame::FrameManager::capture_frame_image`] for how the returned future is meant to be used
+    pub fn capture_output_image(&self, output: &WlOutput) -> impl Future<Output = Result<Image, Error>> + Send + 'static {
+        let mut manager = self.clone();
+        let output = output.clone();
+        async move {
+            let buffer = manager.capture_output(&output)?;
+            Image::new(buffer).map_err(|err| Error::ImageCreate(err.to_string()))
+        }
+    }
+
+    /// capture a single frame buffer of every output
+    ///
+    /// the outputs are captured one after another. a failing capture doesn't abort the remaining captures, instead the
+    /// error is returned alongside the name of the output (which is empty for outputs without name)
+    pub fn capture_all(&self) -> Vec<(String, Result<Buffer, Error>)> {
+        let mut manager = self.clone();
+        self.outputs
+            .iter()
+            .map(|(wl_output, output)| (output.name.clone().unwrap_or_default(), manager.capture_output(wl_output)))
+            .collect()
+    }
+
+    /// get the wayland output with the given name
+    pub fn find_output(&self, name: &str) -> Option<&(WlOutput, Output)> {
+        self.outputs.iter().find(|(_, output)| output.name.as_deref() == Some(name))
+    }
+
+    /// get the wayland output of the hyprland monitor with the given id
+    ///
+    /// see [`find_named_output`] for how outputs which share the name of the monitor are disambiguated
+    #[cfg(feature = "hyprland-rs")]
+    pub fn find_monitor_output(
+        &self,
+        monitors: &[hyprland::data::Monitor],
+        id: hyprland::shared::MonitorId,
+    ) -> Result<&(WlOutput, Output), Error> {
+        let monitor = monitors.iter().find(|m| m.id == id).ok_or_else(|| Error::MonitorNotFound(id.to_string()))?;
+        find_named_output(&self.outputs, &monitor.name, &monitor.description, &monitor.make, &monitor.model)
+            .ok_or_else(|| Error::OutputNotFound(monitor.name.clone()))
+    }
+
+    /// get the shm buffer formats advertised by the compositor
+    pub fn supported_formats(&self) -> &[Format] {
+        &self.formats
+    }
+
+    /// describe the output for the context of capture errors
+    fn target(&self, output: &WlOutput) -> String {
+        match self.outputs.iter().find(|(wl_output, _)| wl_output == output).and_then(|(_, o)| o.name.as_deref()) {
+            Some(name) => format!("output {name}"),
+            None => format!("output {}", output.id()),
+        }
+    }
+
+    fn has_ext_capture(&self) -> bool {
+        self.copy_manager.is_some() && self.source_manager.is_some()
+    }
+
+    /// capture a single frame buffer of an output using the `ext-image-copy-capture-v1` protocol
+    fn capture_output_ext(&mut self, output: &WlOutput, overlay_cursor: bool) -> Result<Buffer, Error> {
+        let (Some(copy_manager), Some(source_manager)) = (self.copy_manager.clone(), self.source_manager.clone()) else {
+            Err(Error::ProtocolNotAvailable(std::any::type_name::<ExtImageCopyCaptureManagerV1>()))?
+        };
+        let Some(shm) = self.shm.clone() else { Err(Error::NoShm)? };
+
+        let session_state = Arc::new(Mutex::new(Session::default()));
+        let mut event_queue = self.connection.new_event_queue();
+        let handle = event_queue.handle();
+        let options = if overlay_cursor { Options::PaintCursors } else { Options::empty() };
+        let source = source_manager.create_source(output, &handle, ());
+        let session = copy_manager.create_session(&source, options, &handle, Arc::downgrade(&session_state));
+
+        let destroy = |session: &ExtImageCopyCaptureSessionV1, source: &ExtImageCaptureSourceV1| {
+            session.destroy();
+            source.destroy();
+        };
+
+        // wait until the compositor has sent all buffer constraints
+        loop {
+            if let Err(err) = event_queue.blocking_dispatch(self) {
+                destroy(&session, &source);
+                Err(err)?;
+            }
+            let current = session_state.lock().expect("lock should not be poisoned");
+            if current.stopped {
+                drop(current);
+                destroy(&session, &source);
+                Err(Error::CaptureFailed { target: self.target(output) })?;
+            }
+            if current.done {
+                break;
+            }
+        }
+
+        let (width, height, format) = {
+            let current = session_state.lock().expect("lock should not be poisoned");
+            let format = PREFERRED_FORMATS
+                .into_iter()
+                .find(|format| current.formats.contains(format))
+                .ok_or_else(|| Error::NoSupportedFormat(current.formats.clone()));
+            (current.width, current.height, format)
+        };
+        let format = match format {
+            Ok(format) => format,
+            Err(err) => {
+                destroy(&session, &source);
+                return Err(err);
+            }
+        };
+
+        let buffer = match Buffer::new(&shm, width, height, width * bytes_per_pixel(format), format, &handle, ()) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                destroy(&session, &source);
+                return Err(err);
+            }
+        };
+        let frame = Arc::new(Mutex::new(Frame::default()));
+        let ext_frame = session.create_frame(&handle, Arc::downgrade(&frame));
+        ext_frame.attach_buffer(&buffer.buffer);
+        ext_frame.damage_buffer(0, 0, width as i32, height as i32);
+        ext_frame.capture();
+        {
+            let mut current = frame.lock().expect("lock should not be poisoned");
+            current.buffer = Some(buffer);
+            current.requested = true;
+        }
+
+        let result = loop {
+            if let Err(err) = event_queue.blocking_dispatch(self) {
+                break Err(err.into());
+            }
+            let mut current = frame.lock().expect("lock should not be poisoned");
+            if let Some(err) = current.error.take() {
+                break Err(err);
+            }
+            if current.ready {
+                break Ok(());
+            }
+        };
+
+        ext_frame.destroy();
+        destroy(&session, &source);
+
+        let frame = Arc::into_inner(frame)
+            .expect("we only exit the loop after waiting blockingly for all dispatchers")
+            .into_inner()
+            .expect("lock should not be poisoned");
+        match (result, frame.buffer) {
+            (Ok(()), Some(buffer)) => Ok(buffer),
+            (Ok(()), None) => unreachable!("the buffer is set before the capture is requested"),
+            (Err(err), buffer) => {
+                if let Some(buffer) = buffer {
+                    buffer.destroy();
+                }
+                Err(err)
+            }
+        }
     }
 
     /// capture a selected region of an output
+    ///
+    /// the region is clamped to the mode dimensions of the output before it's captured. the clamped region is
+    /// returned alongside the buffer
     pub fn capture_output_region(
         &mut self,
         output: &WlOutput,
@@ -103,16 +482,32 @@ impl OutputManager {
         y: i32,
         width: i32,
         height: i32,
-    ) -> Result<Buffer, Error> {
-        let Some(zwlr_manager) = &self.manager else {
+    ) -> Result<(Buffer, Region), Error> {
+        let Some(zwlr_manager) = self.manager.clone() else {
             Err(Error::ProtocolNotAvailable(std::any::type_name::<ZwlrScreencopyManagerV1>()))?
         };
 
+        let requested = Region { x, y, width, height };
+        let size = self.outputs.iter().find(|(wl_output, _)| wl_output == output).and_then(|(_, o)| o.transformed_size());
+        let region = match size {
+            Some((max_width, max_height)) => requested.clamp(max_width, max_height).ok_or(Error::EmptyRegion(requested))?,
+            None => {
+                log::warn!("unable to clamp region {requested:?}: output has no mode");
+                requested
+            }
+        };
+        if region != requested {
+            log::debug!("clamped region {requested:?} to {region:?}");
+        }
+
         let frame = Arc::new(Mutex::new(Frame::default()));
         let mut event_queue = self.connection.new_event_queue();
         let handle = event_queue.handle();
+        let Region { x, y, width, height } = region;
         let zwlr_frame = zwlr_manager.capture_output_region(0, output, x, y, width, height, &handle, Arc::downgrade(&frame));
         self.finish_capture(frame, zwlr_frame, &mut event_queue)
+            .map(|buffer| (buffer, region))
+            .map_err(|err| err.with_target(&self.target(output)))
     }
 
     fn finish_capture(
@@ -122,21 +517,16 @@ impl OutputManager {
         event_queue: &mut EventQueue<OutputManager>,
     ) -> Result<Buffer, Error> {
         loop {
-            if let Err(err) = event_queue.blocking_dispatch(self) {
-                Err(Error::WaylandDispatch(err))?;
-            }
+            event_queue.blocking_dispatch(self)?;
             let frame = frame.clone();
             let mut current = frame.lock().expect("lock should not be poisoned");
-            match (current.ready, current.requested, &current.error, &current.buffer) {
-                (_, _, Some(_), _) | (true, _, _, Some(_)) => {
+            match current.poll() {
+                FrameAction::Destroy | FrameAction::Error => {
                     zwlr_frame.destroy();
                     break;
                 }
-                (false, false, _, Some(buffer)) => {
-                    zwlr_frame.copy(&buffer.buffer);
-                    current.requested = true;
-                }
-                _ => continue,
+                FrameAction::Copy(buffer) => zwlr_frame.copy(&buffer),
+                FrameAction::Continue => continue,
             };
         }
 
@@ -155,6 +545,22 @@ impl OutputManager {
             None => unreachable!("we only exit the loop after waiting blockingly for all dispatchers"),
         }
     }
+
+    /// destroy the internal objects of the output manager
+    pub fn destroy(&mut self) {
+        if let Some(zwlr_manager) = &self.manager {
+            zwlr_manager.destroy();
+            self.manager = None;
+        }
+        if let Some(copy_manager) = &self.copy_manager {
+            copy_manager.destroy();
+            self.copy_manager = None;
+        }
+        if let Some(source_manager) = &self.source_manager {
+            source_manager.destroy();
+            self.source_manager = None;
+        }
+    }
 }
 
 impl Dispatch<wl_registry::WlRegistry, ()> for OutputManager {
@@ -176,6 +582,18 @@ impl Dispatch<wl_registry::WlRegistry, ()> for OutputManager {
                     let manager: ZwlrScreencopyManagerV1 = registry.bind(name, version, handle, ());
                     state.manager = Some(manager);
                 }
+                "ext_image_copy_capture_manager_v1" => {
+                    let manager: ExtImageCopyCaptureManagerV1 = registry.bind(name, version.min(1), handle, ());
+                    state.copy_manager = Some(manager);
+                }
+                "ext_output_image_capture_source_manager_v1" => {
+                    let manager: ExtOutputImageCaptureSourceManagerV1 = registry.bind(name, version.min(1), handle, ());
+                    state.source_manager = Some(manager);
+                }
+                "zxdg_output_manager_v1" => {
+                    let manager: ZxdgOutputManagerV1 = registry.bind(name, version.min(3), handle, ());
+                    state.xdg_output_manager = Some(manager);
+                }
                 "wl_output" => {
                     let output: WlOutput = registry.bind(name, version, handle, ());
                     state.outputs.push((output, Output::default()));
@@ -216,7 +634,7 @@ impl Dispatch<wl_output::WlOutput, ()> for OutputManager {
                 let mode = OutputMode { mode: flags.into_result().expect("should be valid mode"), width, height, refresh };
                 output.mode = Some(mode)
             }
-            wl_output::Event::Scale { factor } => output.scale = Some(factor),
+            wl_output::Event::Scale { factor } => output.scale = Some(factor as f64),
             wl_output::Event::Name { name } => output.name = Some(name),
             wl_output::Event::Description { description } => output.description = Some(description),
             wl_output::Event::Done => state.intialized_outputs += 1,
@@ -225,10 +643,28 @@ impl Dispatch<wl_output::WlOutput, ()> for OutputManager {
     }
 }
 
+impl Dispatch<ZxdgOutputV1, WlOutput> for OutputManager {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZxdgOutputV1,
+        event: <ZxdgOutputV1 as wayland_client::Proxy>::Event,
+        data: &WlOutput,
+        _conn: &wayland_client::Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
+    ) {
+        let Some((_, output)) = state.outputs.iter_mut().find(|(wl_output, _)| wl_output == data) else {
+            return log::debug!("dispatcher for ZxdgOutputV1 was called with event {event:?} for an unknown output");
+        };
+        if let zxdg_output_v1::Event::LogicalSize { width, height } = event {
+            output.logical_size = Some((width, height));
+        }
+    }
+}
+
 impl Dispatch<ZwlrScreencopyFrameV1, Weak<Mutex<Frame>>> for OutputManager {
     fn event(
         state: &mut Self,
-        _proxy: &ZwlrScreencopyFrameV1,
+        proxy: &ZwlrScreencopyFrameV1,
         event: <ZwlrScreencopyFrameV1 as wayland_client::Proxy>::Event,
         data: &Weak<Mutex<Frame>>,
         _conn: &wayland_client::Connection,
@@ -245,15 +681,12 @@ impl Dispatch<ZwlrScreencopyFrameV1, Weak<Mutex<Frame>>> for OutputManager {
             zwlr_screencopy_frame_v1::Event::Buffer { format, width, height, stride } => {
                 let format = match format.into_result() {
                     Ok(format) => format,
-                    Err(err) => return frame.error = Some(Error::ProtocolInvalidEnum(err)),
+                    Err(err) => return frame.error = Some(err.into()),
                 };
-                if let Some(shm) = &state.shm {
-                    match Buffer::new(shm, width, height, stride, format, qhandle, ()) {
-                        Ok(buffer) => frame.buffer = Some(buffer),
-                        Err(err) => frame.error = Some(err),
-                    }
-                } else {
-                    frame.error = Some(Error::ProtocolNotAvailable(std::any::type_name::<WlShm>()));
+                frame.offers.push(BufferOffer { format, width, height, stride });
+                // `buffer_done` was added in version 3, before that the shm buffer is the only offer
+                if proxy.version() < 3 {
+                    frame.finish_offers(state.shm.as_ref(), qhandle);
                 }
             }
             zwlr_screencopy_frame_v1::Event::Flags { .. } => {}
@@ -263,13 +696,136 @@ impl Dispatch<ZwlrScreencopyFrameV1, Weak<Mutex<Frame>>> for OutputManager {
             zwlr_screencopy_frame_v1::Event::Failed => frame.error = Some(Error::Failed),
             zwlr_screencopy_frame_v1::Event::Damage { .. } => {}
             zwlr_screencopy_frame_v1::Event::LinuxDmabuf { .. } => {}
-            zwlr_screencopy_frame_v1::Event::BufferDone => {}
+            zwlr_screencopy_frame_v1::Event::BufferDone => frame.finish_offers(state.shm.as_ref(), qhandle),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlShm, ()> for OutputManager {
+    fn event(
+        state: &mut Self,
+        _proxy: &WlShm,
+        event: <WlShm as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &wayland_client::Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
+    ) {
+        if let wl_shm::Event::Format { format } = event {
+            match format.into_result() {
+                Ok(format) => state.formats.push(format),
+                Err(err) => log::warn!("received invalid shm format: {err}"),
+            }
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureSessionV1, Weak<Mutex<Session>>> for OutputManager {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ExtImageCopyCaptureSessionV1,
+        event: <ExtImageCopyCaptureSessionV1 as wayland_client::Proxy>::Event,
+        data: &Weak<Mutex<Session>>,
+        _conn: &wayland_client::Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
+    ) {
+        let Some(data) = data.upgrade() else {
+            log::debug!(
+                "dispatcher for ExtImageCopyCaptureSessionV1 was called with event {event:?} but session was already dropped"
+            );
+            return;
+        };
+        let mut session = data.lock().expect("lock should not be poisoned");
+        match event {
+            ext_image_copy_capture_session_v1::Event::BufferSize { width, height } => {
+                session.width = width;
+                session.height = height;
+            }
+            ext_image_copy_capture_session_v1::Event::ShmFormat { format } => match format.into_result() {
+                Ok(format) => session.formats.push(format),
+                Err(err) => log::warn!("received invalid shm format: {err}"),
+            },
+            ext_image_copy_capture_session_v1::Event::Done => session.done = true,
+            ext_image_copy_capture_session_v1::Event::Stopped => session.stopped = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureFrameV1, Weak<Mutex<Frame>>> for OutputManager {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ExtImageCopyCaptureFrameV1,
+        event: <ExtImageCopyCaptureFrameV1 as wayland_client::Proxy>::Event,
+        data: &Weak<Mutex<Frame>>,
+        _conn: &wayland_client::Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
+    ) {
+        let Some(data) = data.upgrade() else {
+            log::debug!(
+                "dispatcher for ExtImageCopyCaptureFrameV1 was called with event {event:?} but frame was already dropped"
+            );
+            return;
+        };
+        let mut frame = data.lock().expect("lock should not be poisoned");
+        match event {
+            ext_image_copy_capture_frame_v1::Event::Ready => frame.ready = true,
+            ext_image_copy_capture_frame_v1::Event::Failed { reason } => {
+                log::debug!("ext image copy capture frame failed: {reason:?}");
+                frame.error = Some(Error::Failed);
+            }
             _ => {}
         }
     }
 }
 
-delegate_noop!(OutputManager: ignore WlShm);
 delegate_noop!(OutputManager: ignore WlShmPool);
 delegate_noop!(OutputManager: ignore WlBuffer);
 delegate_noop!(OutputManager: ignore ZwlrScreencopyManagerV1);
+delegate_noop!(OutputManager: ignore ExtImageCopyCaptureManagerV1);
+delegate_noop!(OutputManager: ignore ExtOutputImageCaptureSourceManagerV1);
+delegate_noop!(OutputManager: ignore ExtImageCaptureSourceV1);
+delegate_noop!(OutputManager: ignore ZxdgOutputManagerV1);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(name: &str, description: &str) -> Output {
+        Output { name: Some(name.to_string()), description: Some(description.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn find_named_output_by_name() {
+        let outputs = [("first", output("DP-1", "Dell")), ("second", output("DP-2", "LG"))];
+        assert_eq!(find_named_output(&outputs, "DP-2", "", "", "").map(|(id, _)| *id), Some("second"));
+        assert!(find_named_output(&outputs, "HDMI-A-1", "", "", "").is_none());
+    }
+
+    #[test]
+    fn find_named_output_disambiguates_by_description() {
+        let outputs = [("first", output("DP-1", "Dell")), ("second", output("DP-1", "LG"))];
+        assert_eq!(find_named_output(&outputs, "DP-1", "LG", "", "").map(|(id, _)| *id), Some("second"));
+        assert_eq!(find_named_output(&outputs, "DP-1", "Samsung", "", "").map(|(id, _)| *id), Some("first"));
+    }
+
+    #[test]
+    fn logical_scale_of_fractional_output() {
+        let output = Output {
+            mode: Some(OutputMode { mode: Mode::Current, width: 2560, height: 1440, refresh: 60000 }),
+            logical_size: Some((1707, 960)),
+            ..Default::default()
+        };
+        assert!((output.logical_scale().expect("scale should be known") - 1.5).abs() < 0.001);
+        assert!(Output { logical_size: None, ..output }.logical_scale().is_none());
+    }
+
+    #[test]
+    fn display_output_mode() {
+        let mode = |refresh| OutputMode { mode: Mode::Current, width: 2560, height: 1440, refresh };
+        assert_eq!(mode(144000).to_string(), "2560x1440@144Hz");
+        assert_eq!(mode(59940).to_string(), "2560x1440@59.94Hz");
+        assert_eq!(mode(143856).to_string(), "2560x1440@143.86Hz");
+        assert_eq!(mode(0).to_string(), "2560x1440");
+    }
+}
@@ -1,3 +1,21 @@
+use std::{collections::HashMap, time::Duration};
+
+use wayland_client::{Connection, Dispatch, Proxy, event_created_child, protocol::wl_registry};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+};
+
+use crate::error::Error;
+
+/// Maximum amount of toplevels which are parsed from a window sharing list
+pub const MAX_TOPLEVELS: usize = 1024;
+/// Maximum length in bytes of the class and title of a toplevel in a window sharing list
+pub const MAX_FIELD_LENGTH: usize = 4096;
+
+/// Handle of a toplevel advertised by the `wlr-foreign-toplevel-management` protocol
+pub type ToplevelHandle = ZwlrForeignToplevelHandleV1;
+
 #[derive(Clone, Debug)]
 pub struct Toplevel {
     /// id of the wayland toplevel
@@ -15,35 +33,40 @@ impl Toplevel {
     /// which is set by the hyprland desktop portal
     ///
     /// see: https://github.com/hyprwm/xdg-desktop-portal-hyprland/blob/e09dfe2726c8008f983e45a0aa1a3b7416aaeb8a/src/shared/ScreencopyShared.cpp#L61
+    ///
+    /// at most [`MAX_TOPLEVELS`] toplevels are parsed and classes and titles are truncated to [`MAX_FIELD_LENGTH`] bytes
     pub fn parse_list(toplevel_list: &str) -> Vec<Toplevel> {
         let mut toplevels = Vec::new();
 
+        // splitting at the separators keeps all slices on char boundaries, even for titles with multibyte characters
         let mut str = toplevel_list;
         while !str.is_empty() {
-            let Some(id_sep_pos) = str.find("[HC>]") else {
+            if toplevels.len() >= MAX_TOPLEVELS {
+                log::warn!("window sharing list contains more than {MAX_TOPLEVELS} toplevels: ignoring the remaining ones");
+                break;
+            }
+            let Some((id, rest)) = str.split_once("[HC>]") else {
                 log::warn!("found no toplevel id separator");
                 break;
             };
-            let Ok(id) = str[0..id_sep_pos].parse::<u64>() else {
+            let Ok(id) = id.parse::<u64>() else {
                 log::warn!("toplevel id cannot be parsed to unsigned integer");
                 break;
             };
-            let Some(class_sep_pos) = str.find("[HT>]") else {
+            let Some((class, rest)) = rest.split_once("[HT>]") else {
                 log::warn!("found no toplevel class separator");
                 break;
             };
-            let class = str[id_sep_pos + 5..class_sep_pos].to_string();
-            let Some(title_sep_pos) = str.find("[HE>]") else {
+            let Some((title, rest)) = rest.split_once("[HE>]") else {
                 log::warn!("found no toplevel title separator");
                 break;
             };
-            let title = str[class_sep_pos + 5..title_sep_pos].to_string();
 
             // for compatibility until the next hyprland release we support both, the [HA>] argument and it's absence
-            let window_address = match str.find("[HA>]") {
-                Some(window_sep_pos) => match str[title_sep_pos + 5..window_sep_pos].parse::<u64>() {
+            let window_address = match rest.split_once("[HA>]") {
+                Some((window_address, rest)) => match window_address.parse::<u64>() {
                     Ok(window_address) => {
-                        str = &str[window_sep_pos + 5..];
+                        str = rest;
                         Some(window_address)
                     }
                     Err(_) => {
@@ -53,14 +76,245 @@ impl Toplevel {
                 },
                 None => {
                     log::warn!("found no toplevel window separator");
-                    str = &str[title_sep_pos + 5..];
+                    str = rest;
                     None
                 }
             };
 
+            let class = Self::truncate_field(class, "class", id);
+            let title = Self::truncate_field(title, "title", id);
             toplevels.push(Toplevel { id, class, title, window_address });
         }
 
         return toplevels;
     }
+
+    /// truncate a field of the window sharing list to at most [`MAX_FIELD_LENGTH`] bytes on a char boundary
+    fn truncate_field(field: &str, name: &str, id: u64) -> String {
+        if field.len() <= MAX_FIELD_LENGTH {
+            return field.to_string();
+        }
+        log::warn!("{name} of toplevel {id} is longer than {MAX_FIELD_LENGTH} bytes: truncating it");
+        let end = (0..=MAX_FIELD_LENGTH).rev().find(|index| field.is_char_boundary(*index)).unwrap_or_default();
+        field[..end].to_string()
+    }
+
+    /// Parse a window sharing list from raw bytes
+    ///
+    /// returns an error when the bytes aren't valid utf-8 instead of lossily replacing the invalid sequences
+    pub fn parse_list_bytes(toplevel_list: &[u8]) -> Result<Vec<Toplevel>, Error> {
+        let toplevel_list = std::str::from_utf8(toplevel_list)?;
+        Ok(Self::parse_list(toplevel_list))
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct ForeignToplevel {
+    pub title: Option<String>,
+    pub app_id: Option<String>,
+    /// whether the compositor has sent all initial properties of the toplevel
+    pub done: bool,
+    /// whether the toplevel was closed in the meantime
+    pub closed: bool,
+}
+
+#[derive(Clone)]
+pub struct ToplevelManager {
+    manager: Option<ZwlrForeignToplevelManagerV1>,
+    pub toplevels: Vec<(ZwlrForeignToplevelHandleV1, ForeignToplevel)>,
+}
+
+impl ToplevelManager {
+    /// setup a new toplevel manager which collects all toplevels advertised by the `wlr-foreign-toplevel-management`
+    /// protocol
+    pub fn new(connection: &Connection) -> Result<Self, Error> {
+        let display = connection.display();
+
+        let mut event_queue = connection.new_event_queue();
+        let handle = event_queue.handle();
+
+        let mut manager = Self { manager: None, toplevels: Vec::new() };
+
+        display.get_registry(&handle, ());
+
+        event_queue.roundtrip(&mut manager)?;
+
+        if let None = manager.manager {
+            Err(Error::ProtocolNotAvailable(std::any::type_name::<ZwlrForeignToplevelManagerV1>()))?
+        }
+
+        // the first roundtrip announces the toplevels, the second one their properties
+        event_queue.roundtrip(&mut manager)?;
+        event_queue.roundtrip(&mut manager)?;
+
+        Ok(manager)
+    }
+
+    /// setup a new toplevel manager like [`ToplevelManager::new`] but give up when the compositor doesn't answer the
+    /// roundtrips within `timeout`
+    pub fn new_with_timeout(connection: &Connection, timeout: Duration) -> Result<Self, Error> {
+        let connection = connection.clone();
+        crate::with_timeout(timeout, move || Self::new(&connection))
+    }
+
+    /// get all toplevels which are still open
+    ///
+    /// the wayland protocol id of the toplevel handle is used as id since the toplevel isn't known to the
+    /// hyprland desktop portal. therefore no window address is available either
+    pub fn get_toplevels(&self) -> Vec<Toplevel> {
+        self.toplevels
+            .iter()
+            .filter(|(_, toplevel)| !toplevel.closed)
+            .map(|(handle, toplevel)| Toplevel {
+                id: handle.id().protocol_id() as u64,
+                class: toplevel.app_id.clone().unwrap_or_default(),
+                title: toplevel.title.clone().unwrap_or_default(),
+                window_address: None,
+            })
+            .collect()
+    }
+
+    /// map the ids of the toplevels to the handles of the protocol toplevels with the same class and title
+    ///
+    /// the desktop portal builds the sharing list from the same protocol, therefore toplevels with the same class and
+    /// title are paired in the order they were advertised. groups whose sizes differ between the toplevels and the
+    /// protocol are left out since their order can't be trusted
+    pub fn match_handles(&self, toplevels: &[Toplevel]) -> HashMap<u64, ToplevelHandle> {
+        let mut groups = HashMap::<(&str, &str), Vec<&ToplevelHandle>>::new();
+        self.toplevels.iter().filter(|(_, toplevel)| !toplevel.closed).for_each(|(handle, toplevel)| {
+            let key = (toplevel.app_id.as_deref().unwrap_or_default(), toplevel.title.as_deref().unwrap_or_default());
+            groups.entry(key).or_default().push(handle);
+        });
+
+        let mut matched = HashMap::new();
+        groups.into_iter().for_each(|((class, title), handles)| {
+            let ids = toplevels.iter().filter(|toplevel| toplevel.class == class && toplevel.title == title);
+            if ids.clone().count() != handles.len() {
+                return log::debug!("unable to pair toplevels with class {class} and title {title} to their handles");
+            }
+            ids.zip(handles).for_each(|(toplevel, handle)| {
+                matched.insert(toplevel.id, handle.clone());
+            });
+        });
+        matched
+    }
+
+    /// destroy the internal objects of the toplevel manager
+    pub fn destroy(&mut self) {
+        self.toplevels.iter().for_each(|(handle, _)| handle.destroy());
+        self.toplevels.clear();
+        if let Some(manager) = &self.manager {
+            manager.stop();
+            self.manager = None;
+        }
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, ()> for ToplevelManager {
+    fn event(
+        state: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: <wl_registry::WlRegistry as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        handle: &wayland_client::QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global { name, interface, version } => match interface.as_str() {
+                "zwlr_foreign_toplevel_manager_v1" => {
+                    let manager: ZwlrForeignToplevelManagerV1 = registry.bind(name, version.min(3), handle, ());
+                    state.manager = Some(manager);
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for ToplevelManager {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZwlrForeignToplevelManagerV1,
+        event: <ZwlrForeignToplevelManagerV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } => {
+                state.toplevels.push((toplevel, ForeignToplevel::default()));
+            }
+            zwlr_foreign_toplevel_manager_v1::Event::Finished => {
+                state.manager = None;
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(ToplevelManager, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for ToplevelManager {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrForeignToplevelHandleV1,
+        event: <ZwlrForeignToplevelHandleV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
+    ) {
+        let Some((_, toplevel)) = state.toplevels.iter_mut().find(|(handle, _)| handle == proxy) else {
+            log::debug!("dispatcher for ZwlrForeignToplevelHandleV1 was called with event {event:?} for unknown toplevel");
+            return;
+        };
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Title { title } => toplevel.title = Some(title),
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => toplevel.app_id = Some(app_id),
+            zwlr_foreign_toplevel_handle_v1::Event::Done => toplevel.done = true,
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => toplevel.closed = true,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_list_with_multibyte_titles() {
+        let list = "1[HC>]kitty[HT>]日本語のタイトル[HE>]42[HA>]2[HC>]firefox[HT>]🦀 crab 🦀[HE>]43[HA>]";
+        let toplevels = Toplevel::parse_list(list);
+        assert_eq!(toplevels.len(), 2);
+        assert_eq!(toplevels[0].title, "日本語のタイトル");
+        assert_eq!(toplevels[0].window_address, Some(42));
+        assert_eq!(toplevels[1].class, "firefox");
+        assert_eq!(toplevels[1].title, "🦀 crab 🦀");
+        assert_eq!(toplevels[1].window_address, Some(43));
+    }
+
+    #[test]
+    fn parse_list_bytes_rejects_invalid_utf8() {
+        assert!(Toplevel::parse_list_bytes(b"1[HC>]kitty[HT>]\xf0\x9f[HE>]").is_err());
+        let toplevels = Toplevel::parse_list_bytes("1[HC>]kitty[HT>]é[HE>]".as_bytes()).expect("should be valid utf-8");
+        assert_eq!(toplevels[0].title, "é");
+    }
+
+    #[test]
+    fn parse_list_limits_toplevels() {
+        let list = "1[HC>]kitty[HT>]title[HE>]42[HA>]".repeat(MAX_TOPLEVELS + 1);
+        assert_eq!(Toplevel::parse_list(&list).len(), MAX_TOPLEVELS);
+    }
+
+    #[test]
+    fn parse_list_truncates_long_fields() {
+        // the multibyte character straddles the limit and must not be split
+        let title = format!("{}é", "a".repeat(MAX_FIELD_LENGTH - 1));
+        let toplevels = Toplevel::parse_list(&format!("1[HC>]kitty[HT>]{title}[HE>]42[HA>]"));
+        assert_eq!(toplevels[0].title.len(), MAX_FIELD_LENGTH - 1);
+        assert_eq!(toplevels[0].class, "kitty");
+    }
 }
@@ -1,35 +1,119 @@
-use std::{cell::RefCell, process::exit, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    process::exit,
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
 
-use glib::variant::StaticVariantType;
+use glib::variant::{StaticVariantType, ToVariant};
 use gtk4::{
-    Application, ApplicationWindow, Box, CheckButton, CssProvider, EventControllerKey, Notebook,
-    STYLE_PROVIDER_PRIORITY_APPLICATION, Widget,
-    gdk::Display,
+    Application, ApplicationWindow, Box, Button, CheckButton, CssProvider, EventControllerFocus, EventControllerKey,
+    EventControllerLegacy, Notebook, STYLE_PROVIDER_PRIORITY_APPLICATION, ScrolledWindow, SearchEntry, Widget,
+    gdk::{Display, Key, ModifierType},
     gio::{
-        ActionEntry,
-        prelude::{ActionMapExtManual, ApplicationExt, ApplicationExtManual},
+        ActionEntry, SimpleAction,
+        prelude::{ActionExt, ActionMapExt, ActionMapExtManual, ApplicationExt, ApplicationExtManual},
+    },
+    glib::{
+        ExitCode, Propagation, SourceId, clone,
+        object::{CastNone, IsA},
     },
-    glib::{ExitCode, clone, object::IsA},
-    prelude::{BoxExt, CheckButtonExt, GtkWindowExt, WidgetExt},
+    prelude::{BoxExt, CheckButtonExt, EditableExt, GtkWindowExt, WidgetExt},
 };
 use gtk4_layer_shell::*;
-use hyprland_preview_share_picker_lib::toplevel::Toplevel;
+use hyprland_preview_share_picker_lib::{
+    frame::FrameManager,
+    output::OutputManager,
+    toplevel::{Toplevel, ToplevelManager},
+};
 use rsass::{compile_scss, output};
-use wayland_client::Connection;
+use tokio::task::JoinHandle;
+use wayland_client::{ConnectError, Connection};
 
 use crate::{
     config::{self, Config},
-    views::{View, outputs::OutputsView, region::RegionView, windows::WindowsView},
+    reconnect::Reconnect,
+    sink::SelectionSink,
+    stats::WindowStats,
+    util::{client_handle, get_active_client, get_clients, get_monitors},
+    views::{
+        CaptureProgress, PageFilter, View,
+        outputs::{DeferredCapture, OutputsView},
+        region::RegionView,
+        tab_label,
+        windows::WindowsView,
+    },
 };
 
 const APP_ID: &str = "ch.wysbd.hyprland-preview-share-picker";
+/// maximum time the window waits for the output captures before being presented
+const CAPTURE_BEFORE_PRESENT_TIMEOUT: Duration = Duration::from_secs(2);
+/// maximum time the capture progress bar is shown before it's hidden even though captures are still pending
+const CAPTURE_PROGRESS_TIMEOUT: Duration = Duration::from_secs(10);
+/// maximum time to wait for the toplevels advertised by the wayland protocol
+const PROTOCOL_TOPLEVELS_TIMEOUT: Duration = Duration::from_secs(1);
+/// maximum time to wait for the outputs advertised by the wayland protocol
+const PROTOCOL_OUTPUTS_TIMEOUT: Duration = Duration::from_secs(2);
 
 pub struct App {
     gtk_app: Application,
 }
 
+/// Wayland connection shared by all views together with the protocol managers
+struct Wayland {
+    connection: Connection,
+    frame_manager: Result<Arc<FrameManager>, String>,
+    output_manager: Result<Arc<OutputManager>, String>,
+    toplevel_manager: Result<ToplevelManager, String>,
+}
+
+impl Wayland {
+    /// connect to the wayland server and set up the protocol managers on a separate thread
+    ///
+    /// this runs the registry roundtrips whilst gtk is starting up instead of delaying the first frame of the window
+    fn prewarm() -> Result<std::thread::JoinHandle<Self>, ConnectError> {
+        let connection = Connection::connect_to_env()?;
+        Ok(std::thread::spawn(move || {
+            let frame_manager = FrameManager::new(&connection)
+                .map(Arc::new)
+                .map_err(|err| format!("unable to create new frame manager from connection: {err}"));
+            let output_manager = OutputManager::new_with_timeout(&connection, PROTOCOL_OUTPUTS_TIMEOUT)
+                .map(Arc::new)
+                .map_err(|err| format!("unable to create new output manager from connection: {err}"));
+            let toplevel_manager = ToplevelManager::new_with_timeout(&connection, PROTOCOL_TOPLEVELS_TIMEOUT)
+                .map_err(|err| format!("unable to get toplevels from wayland protocol: {err}"));
+            Self { connection, frame_manager, output_manager, toplevel_manager }
+        }))
+    }
+}
+
+/// Pages which are shown in the notebook
+#[derive(Clone, Copy)]
+pub struct Pages {
+    pub windows: bool,
+    pub outputs: bool,
+    pub region: bool,
+}
+
+impl Pages {
+    /// check whether at least one page is shown
+    pub fn any(&self) -> bool {
+        self.windows || self.outputs || self.region
+    }
+}
+
 impl App {
-    pub fn build(interactive_debug: bool, config: Config, toplevels: Vec<Toplevel>, restore_token: bool) -> Self {
+    pub fn build(
+        interactive_debug: bool,
+        config: Config,
+        toplevels: Vec<Toplevel>,
+        restore_token: bool,
+        pages: Pages,
+        multi: bool,
+        sink: SelectionSink,
+    ) -> Self {
         let gtk_app = Application::builder().application_id(APP_ID).build();
 
         let app = Self { gtk_app };
@@ -50,9 +134,21 @@ impl App {
             }
         }
 
+        let wayland = match Wayland::prewarm() {
+            Ok(wayland) => RefCell::new(Some(wayland)),
+            Err(err) => {
+                log::error!("unable to connect to wayland server: {err}");
+                exit(1);
+            }
+        };
+
         app.gtk_app.connect_activate(move |app| {
             log::debug!("gtk app is activated");
-            build_ui(app, &config, &toplevels, restore_token);
+            let Some(wayland) = wayland.take() else {
+                return log::warn!("gtk app was activated more than once");
+            };
+            let wayland = wayland.join().expect("wayland setup thread should not panic");
+            build_ui(app, &config, &wayland, &toplevels, restore_token, pages, multi, &sink);
         });
 
         app
@@ -64,72 +160,249 @@ impl App {
     }
 }
 
-fn build_ui(app: &Application, config: &Config, toplevels: &[Toplevel], default_restore_token: bool) {
+#[allow(clippy::too_many_arguments)]
+fn build_ui(
+    app: &Application,
+    config: &Config,
+    wayland: &Wayland,
+    toplevels: &[Toplevel],
+    default_restore_token: bool,
+    pages: Pages,
+    multi: bool,
+    sink: &SelectionSink,
+) {
     let window = build_window(app, config);
     log::debug!("built application window");
     let window_container = Box::new(gtk4::Orientation::Vertical, 0);
     window.set_child(Some(&window_container));
 
-    let con = match Connection::connect_to_env() {
-        Ok(connection) => connection,
-        Err(err) => {
-            log::error!("unable to connect to wayland server: {err}");
-            exit(1);
-        }
-    };
+    let toplevels = Rc::new(if !pages.windows {
+        Vec::new()
+    } else if toplevels.is_empty() {
+        log::warn!("received no toplevels from the sharing list: falling back to toplevels from the wayland protocol");
+        get_protocol_toplevels(&wayland.toplevel_manager)
+    } else {
+        toplevels.to_vec()
+    });
 
     let restore_token = Rc::new(RefCell::new(default_restore_token));
-    let exit_action = ActionEntry::builder("select")
-        .parameter_type(Some(&String::static_variant_type()))
+    let emit_grim_geometry = config.region.emit_grim_geometry;
+    let count_selections = config.windows.sort_by == config::WindowSort::Frequency;
+    let sink = sink.clone();
+    let mut exit_action = ActionEntry::builder("select").parameter_type(Some(&String::static_variant_type()));
+    if multi {
+        // the state holds the selected parameters which are emitted once the selection is confirmed
+        exit_action = exit_action.state(Vec::<String>::new().to_variant());
+    }
+    let exit_action = exit_action
         .activate(clone!(
             #[strong]
             restore_token,
-            move |_: &ApplicationWindow, _, parameter| {
-                let allow_restore_token = *restore_token.borrow();
+            #[strong]
+            sink,
+            #[strong]
+            toplevels,
+            move |_: &ApplicationWindow, action, parameter| {
                 let parameter = parameter
                     .expect("win.select called without parameter")
                     .get::<String>()
                     .expect("parameter of win.select action should be a string");
-                println!("[SELECTION]{}/{parameter}", if allow_restore_token { "r" } else { "" });
-                exit(0);
+                if multi {
+                    let mut selected = selected_parameters(action);
+                    toggle_selection(&mut selected, parameter);
+                    return action.set_state(&selected.to_variant());
+                }
+                if count_selections {
+                    record_window_stats(std::slice::from_ref(&parameter), &toplevels);
+                }
+                emit_selections(&sink, *restore_token.borrow(), &[parameter], emit_grim_geometry);
+            }
+        ))
+        .build();
+    let confirm_action = ActionEntry::builder("confirm")
+        .activate(clone!(
+            #[strong]
+            restore_token,
+            #[strong]
+            sink,
+            #[strong]
+            toplevels,
+            move |window: &ApplicationWindow, _, _| {
+                let Some(action) = window.lookup_action("select").and_downcast::<SimpleAction>() else {
+                    return log::error!("unable to confirm selection: select action is not registered");
+                };
+                let selected = selected_parameters(&action);
+                if selected.is_empty() {
+                    return log::warn!("unable to confirm selection: nothing is selected");
+                }
+                if count_selections {
+                    record_window_stats(&selected, &toplevels);
+                }
+                emit_selections(&sink, *restore_token.borrow(), &selected, emit_grim_geometry);
             }
         ))
         .build();
-    window.add_action_entries([exit_action]);
+    window.add_action_entries([exit_action, confirm_action]);
 
     let notebook = Notebook::builder().css_classes([config.classes.notebook.as_str()]).vexpand(true).build();
 
-    match WindowsView::new(&con, toplevels, config) {
-        Ok(view) => {
-            let page_num = notebook.append_page(&view.build(), Some(&view.label()));
-            if let config::Page::Windows = config.default_page {
-                notebook.set_current_page(Some(page_num));
-            }
-        }
-        Err(err) => log::error!("unable to build windows view: {err}"),
-    };
+    // query hyprland only once for both views
+    let clients = Rc::new(get_clients().unwrap_or_else(|err| {
+        log::error!("{err}");
+        Vec::new()
+    }));
+    let monitors = Rc::new(get_monitors().unwrap_or_else(|err| {
+        log::error!("{err}");
+        Vec::new()
+    }));
 
-    match OutputsView::new(&con, config) {
-        Ok(view) => {
-            let page_num = notebook.append_page(&view.build(), Some(&view.label()));
-            if let config::Page::Outputs = config.default_page {
-                notebook.set_current_page(Some(page_num));
+    let shared_config = Rc::new(config.clone());
+    let progress = CaptureProgress::new(config.show_progress, &config.classes.progress_bar);
+    let mut builders = Vec::<(config::Page, PageBuilder)>::new();
+    if pages.windows {
+        // the handles allow capturing toplevels without window address which share their class and title
+        let handles = match &wayland.toplevel_manager {
+            Ok(manager) => manager.match_handles(&toplevels),
+            Err(_) => HashMap::new(),
+        };
+
+        let manager = match wayland.frame_manager.clone() {
+            Ok(manager) => Ok(Some(manager)),
+            Err(err) if config.windows.icon_fallback => {
+                log::warn!("{err}: showing application icons instead of previews");
+                Ok(None)
             }
+            Err(err) => Err(err),
+        };
+        // captures which fail because the connection was lost are retried once on a new connection
+        let reconnect = Reconnect::new(FrameManager::new);
+        match manager {
+            Ok(manager) => builders.push((
+                config::Page::Windows,
+                std::boxed::Box::new(clone!(
+                    #[strong]
+                    clients,
+                    #[strong]
+                    monitors,
+                    #[strong]
+                    progress,
+                    #[strong]
+                    shared_config,
+                    #[strong]
+                    toplevels,
+                    move || {
+                        let view = WindowsView::new(
+                            manager,
+                            reconnect,
+                            &toplevels,
+                            handles,
+                            &clients,
+                            &monitors,
+                            progress,
+                            &shared_config,
+                        );
+                        BuiltPage::new(&view)
+                    }
+                )),
+            )),
+            Err(err) => log::error!("unable to build windows view: {err}"),
+        };
+    }
+
+    if pages.outputs {
+        let reconnect = Reconnect::new(|connection| OutputManager::new_with_timeout(connection, PROTOCOL_OUTPUTS_TIMEOUT));
+        match wayland.output_manager.clone() {
+            Ok(manager) => builders.push((
+                config::Page::Outputs,
+                std::boxed::Box::new(clone!(
+                    #[strong]
+                    monitors,
+                    #[strong]
+                    progress,
+                    #[strong]
+                    shared_config,
+                    move || {
+                        let view = OutputsView::new(manager, reconnect, &monitors, progress, &shared_config);
+                        let mut page = BuiltPage::new(&view);
+                        if shared_config.outputs.capture_before_present {
+                            page.capture_tasks = view.take_capture_tasks();
+                        }
+                        page.deferred_captures = view.take_deferred_captures();
+                        page
+                    }
+                )),
+            )),
+            Err(err) => log::error!("unable to build outputs view: {err}"),
         }
-        Err(err) => log::error!("unable to build outputs view: {err}"),
     }
 
-    match RegionView::new(config) {
-        Ok(view) => {
-            let page_num = notebook.append_page(&view.build(), Some(&view.label()));
-            if let config::Page::Region = config.default_page {
-                notebook.set_current_page(Some(page_num));
-            }
+    if pages.region {
+        // the region command is validated right away such that an invalid command doesn't leave an empty page behind
+        match RegionView::new(config) {
+            Ok(_) => builders.push((
+                config::Page::Region,
+                std::boxed::Box::new(clone!(
+                    #[strong]
+                    shared_config,
+                    move || {
+                        let view = RegionView::new(&shared_config).expect("region command should have been validated");
+                        BuiltPage::new(&view)
+                    }
+                )),
+            )),
+            Err(err) => log::error!("unable to build region view: {err}"),
+        };
+    }
+
+    let default_index = builders.iter().position(|(page, _)| *page == config.default_page).unwrap_or(0);
+    let mut filters = Vec::new();
+    let mut capture_tasks = Vec::new();
+    let mut deferred_captures = None;
+    let mut lazy_pages = Vec::new();
+    for (index, (page, build)) in builders.into_iter().enumerate() {
+        if config.lazy_pages && index != default_index {
+            let container = Box::new(gtk4::Orientation::Vertical, 0);
+            let page_num = notebook.append_page(&container, Some(&tab_label(page, config)));
+            lazy_pages.push((page_num, container, build));
+            continue;
         }
-        Err(err) => log::error!("unable to build region view: {err}"),
-    };
+        let built = build();
+        let page_num = notebook.append_page(&built.widget, Some(&tab_label(page, config)));
+        if let Some(filter) = built.filter {
+            filters.push((page_num, filter));
+        }
+        capture_tasks.extend(built.capture_tasks);
+        if !built.deferred_captures.is_empty() {
+            deferred_captures = Some((page_num, built.deferred_captures));
+        }
+    }
+    if notebook.n_pages() > 0 {
+        notebook.set_current_page(Some(default_index as u32));
+    }
+    filters.extend(build_lazily(&notebook, lazy_pages));
 
+    if config.show_progress {
+        window_container.append(progress.widget());
+        glib::timeout_add_local_once(CAPTURE_PROGRESS_TIMEOUT, move || progress.hide());
+    }
+    if config.show_search {
+        let search_entry = build_search_entry(&notebook, filters, config);
+        window_container.append(&search_entry);
+    }
     window_container.append(&notebook);
+    add_page_cycling(&window, &notebook, &config.window.cycle_pages_key);
+    if let Some((page_num, captures)) = deferred_captures {
+        spawn_deferred_captures(&notebook, page_num, captures);
+    }
+
+    if multi {
+        let confirm_button = Button::builder()
+            .css_classes([config.classes.confirm_button.as_str()])
+            .label("Confirm selection")
+            .action_name("win.confirm")
+            .build();
+        window_container.append(&confirm_button);
+    }
 
     if !config.hide_token_restore {
         log::debug!("building token restore widget");
@@ -137,8 +410,239 @@ fn build_ui(app: &Application, config: &Config, toplevels: &[Toplevel], default_
         window_container.append(&restore_button);
     }
 
-    log::debug!("presenting window");
-    window.present();
+    if capture_tasks.is_empty() {
+        log::debug!("presenting window");
+        return window.present();
+    }
+    // the picker is an overlay, therefore it would be visible in the output captures if it was presented right away
+    glib::spawn_future_local(async move {
+        let captures = async {
+            for task in capture_tasks {
+                let _ = task.await;
+            }
+        };
+        if tokio::time::timeout(CAPTURE_BEFORE_PRESENT_TIMEOUT, captures).await.is_err() {
+            log::warn!("output captures did not finish in time: presenting window anyway");
+        }
+        log::debug!("presenting window");
+        window.present();
+    });
+}
+
+/// Builds the view of a notebook page
+type PageBuilder = std::boxed::Box<dyn FnOnce() -> BuiltPage>;
+
+/// View of a notebook page which was built together with the captures it started
+struct BuiltPage {
+    widget: ScrolledWindow,
+    filter: Option<PageFilter>,
+    /// tasks capturing the previews which are awaited before the picker is presented
+    capture_tasks: Vec<JoinHandle<()>>,
+    /// captures which are spawned once the page is shown
+    deferred_captures: Vec<DeferredCapture>,
+}
+
+impl BuiltPage {
+    fn new(view: &impl View) -> Self {
+        let widget = view.build();
+        Self { widget, filter: view.filter(), capture_tasks: Vec::new(), deferred_captures: Vec::new() }
+    }
+}
+
+/// Notebook page whose view is built once the page is shown or searched for the first time
+struct LazyPage {
+    container: Box,
+    build: RefCell<Option<PageBuilder>>,
+    filter: RefCell<Option<PageFilter>>,
+}
+
+impl LazyPage {
+    /// build the view of the page unless it was built already
+    fn build(&self) {
+        let Some(build) = self.build.take() else {
+            return;
+        };
+        let built = build();
+        built.widget.set_vexpand(true);
+        self.container.append(&built.widget);
+        *self.filter.borrow_mut() = built.filter;
+        // the picker is already presented, therefore all captures are spawned right away
+        built.deferred_captures.into_iter().for_each(|capture| {
+            tokio::spawn(capture);
+        });
+    }
+
+    fn is_built(&self) -> bool {
+        self.build.borrow().is_none()
+    }
+}
+
+/// Build the views of the pages once they're shown for the first time
+///
+/// the returned filters build their page before filtering it such that the search still matches the entries of pages
+/// which weren't shown yet
+fn build_lazily(notebook: &Notebook, pages: Vec<(u32, Box, PageBuilder)>) -> Vec<(u32, PageFilter)> {
+    pages
+        .into_iter()
+        .map(|(page_num, container, build)| {
+            let lazy = Rc::new(LazyPage { container, build: RefCell::new(Some(build)), filter: RefCell::new(None) });
+            notebook.connect_switch_page(clone!(
+                #[strong]
+                lazy,
+                move |_, _, page| {
+                    if page == page_num {
+                        lazy.build();
+                    }
+                }
+            ));
+            let filter: PageFilter = std::boxed::Box::new(move |query| {
+                // an empty query only resets the filters, which isn't needed for pages which weren't built yet
+                if query.is_empty() && !lazy.is_built() {
+                    return false;
+                }
+                lazy.build();
+                lazy.filter.borrow().as_ref().is_some_and(|filter| filter(query))
+            });
+            (page_num, filter)
+        })
+        .collect()
+}
+
+/// Spawn the deferred output captures once the outputs page is shown for the first time
+///
+/// the captures are taken out of the cell when spawned, therefore switching to the page again doesn't capture twice
+fn spawn_deferred_captures(notebook: &Notebook, page_num: u32, captures: Vec<DeferredCapture>) {
+    let captures = RefCell::new(captures);
+    let spawn = move || {
+        let captures = captures.take();
+        if captures.is_empty() {
+            return;
+        }
+        log::debug!("spawning {} deferred output captures", captures.len());
+        captures.into_iter().for_each(|capture| {
+            tokio::spawn(capture);
+        });
+    };
+    if notebook.current_page() == Some(page_num) {
+        return spawn();
+    }
+    notebook.connect_switch_page(move |_, _, page| {
+        if page == page_num {
+            spawn();
+        }
+    });
+}
+
+/// Get the toplevels advertised by the `wlr-foreign-toplevel-management` protocol
+///
+/// the ids of these toplevels are not known to the hyprland desktop portal, therefore the sharing list is preferred.
+/// when the protocol is unavailable or the compositor didn't answer in time the windows page stays empty
+fn get_protocol_toplevels(manager: &Result<ToplevelManager, String>) -> Vec<Toplevel> {
+    match manager {
+        Ok(manager) => {
+            let toplevels = manager.get_toplevels();
+            log::debug!("got toplevels from protocol {toplevels:#?}");
+            toplevels
+        }
+        Err(err) => {
+            log::error!("{err}");
+            Vec::new()
+        }
+    }
+}
+
+/// Get the selection of the toplevel which belongs to the currently focused hyprland client
+///
+/// the toplevel is matched by window address and falls back to class and title for toplevels without an address
+pub fn select_active_window(toplevels: &[Toplevel], allow_token: bool) -> Result<String, String> {
+    let client = get_active_client()?.ok_or("there is no active window")?;
+    let handle = client_handle(&client).map_err(|err| format!("unable to convert client address to u64: {err}"))?;
+    let toplevel = toplevels
+        .iter()
+        .find(|toplevel| toplevel.window_address == Some(handle))
+        .or_else(|| {
+            toplevels.iter().find(|toplevel| {
+                toplevel.window_address.is_none() && toplevel.class == client.class && toplevel.title == client.title
+            })
+        })
+        .ok_or(format!("the active window {} is not in the sharing list", client.address))?;
+    Ok(format_selection(allow_token, &format!("window:{}", toplevel.id)))
+}
+
+/// Run the region command without launching the picker and get the selection of the region
+///
+/// returns `None` when the region selection was cancelled
+pub fn select_region(config: &Config, allow_token: bool) -> Result<Option<String>, String> {
+    let Some(region) = RegionView::new(config)?.select()? else {
+        return Ok(None);
+    };
+    if config.region.emit_grim_geometry
+        && let Some(geometry) = grim_geometry(&region)
+    {
+        // stdout is parsed by the portal, therefore the geometry is written to stderr
+        eprintln!("{geometry}");
+    }
+    Ok(Some(format_selection(allow_token, &format!("region:{region}"))))
+}
+
+/// Format the selection in the `[SELECTION]<flags>/<type>:<value>` format which is parsed by the portal
+///
+/// the `r` flag tells the portal to allow a restore token for the selection
+fn format_selection(allow_token: bool, parameter: &str) -> String {
+    format!("[SELECTION]{}/{parameter}", if allow_token { "r" } else { "" })
+}
+
+/// Write the selections to the sink and exit
+///
+/// every selection is written on its own line. the grim geometry of selected regions is written to stderr when enabled
+fn emit_selections(sink: &SelectionSink, allow_token: bool, parameters: &[String], emit_grim_geometry: bool) -> ! {
+    let selection = parameters.iter().map(|parameter| format_selection(allow_token, parameter)).collect::<Vec<_>>();
+    if let Err(err) = sink.emit(&selection.join("\n")) {
+        log::error!("unable to write selection: {err}");
+        exit(1);
+    }
+    if emit_grim_geometry {
+        parameters
+            .iter()
+            .filter_map(|parameter| parameter.strip_prefix("region:"))
+            .filter_map(grim_geometry)
+            // stdout is parsed by the portal, therefore the geometry is written to stderr
+            .for_each(|geometry| eprintln!("{geometry}"));
+    }
+    exit(0);
+}
+
+/// count the classes of the selected windows in the window stats which are used for sorting the windows by frequency
+fn record_window_stats(parameters: &[String], toplevels: &[Toplevel]) {
+    let mut stats = WindowStats::load();
+    stats.record(parameters, toplevels);
+    if let Err(err) = stats.save() {
+        log::warn!("unable to save window stats: {err}");
+    }
+}
+
+/// get the parameters which are selected in the state of the select action in multi select mode
+fn selected_parameters(action: &SimpleAction) -> Vec<String> {
+    action.state().and_then(|state| state.get::<Vec<String>>()).unwrap_or_default()
+}
+
+/// add the parameter to the selection or remove it when it's already selected
+fn toggle_selection(selected: &mut Vec<String>, parameter: String) {
+    match selected.iter().position(|selected| *selected == parameter) {
+        Some(index) => {
+            selected.remove(index);
+        }
+        None => selected.push(parameter),
+    }
+}
+
+/// Convert a `<output>@<x>,<y>,<w>,<h>` region into the `<x>,<y> <w>x<h>` geometry format used by grim
+fn grim_geometry(region: &str) -> Option<String> {
+    let (_, geometry) = region.rsplit_once('@')?;
+    let &[x, y, w, h] = geometry.split(',').collect::<Vec<_>>().as_slice() else {
+        return None;
+    };
+    Some(format!("{x},{y} {w}x{h}"))
 }
 
 fn load_stylesheets(config: &Config) {
@@ -199,15 +703,175 @@ fn build_window(app: &Application, config: &Config) -> ApplicationWindow {
     });
     window.add_controller(event_controller);
 
+    if let Some(timeout_s) = config.window.idle_timeout_s
+        && timeout_s > 0
+    {
+        add_idle_timeout(&window, timeout_s);
+    }
+
+    if config.window.close_on_focus_loss {
+        add_close_on_focus_loss(&window);
+    }
+
     window.init_layer_shell();
     window.set_namespace(APP_ID);
-    window.set_layer(Layer::Overlay);
-    window.set_keyboard_mode(KeyboardMode::OnDemand);
+    window.set_layer(config.window.layer.into());
+    window.set_keyboard_mode(config.window.keyboard_mode.into());
     window.set_exclusive_zone(-1);
 
     window
 }
 
+impl From<config::Layer> for Layer {
+    fn from(value: config::Layer) -> Self {
+        match value {
+            config::Layer::Background => Layer::Background,
+            config::Layer::Bottom => Layer::Bottom,
+            config::Layer::Top => Layer::Top,
+            config::Layer::Overlay => Layer::Overlay,
+        }
+    }
+}
+
+impl From<config::KeyboardMode> for KeyboardMode {
+    fn from(value: config::KeyboardMode) -> Self {
+        match value {
+            config::KeyboardMode::None => KeyboardMode::None,
+            config::KeyboardMode::Exclusive => KeyboardMode::Exclusive,
+            config::KeyboardMode::OnDemand => KeyboardMode::OnDemand,
+        }
+    }
+}
+
+/// Close the picker as cancelled after `timeout_s` seconds without any input on the window
+fn add_idle_timeout(window: &ApplicationWindow, timeout_s: u32) {
+    let timeout = RefCell::new(None::<SourceId>);
+    let reset_timeout = move || {
+        if let Some(source) = timeout.borrow_mut().take() {
+            source.remove();
+        }
+        let source = glib::timeout_add_seconds_local_once(timeout_s, move || {
+            log::info!("exiting: no input for {timeout_s} seconds");
+            exit(1);
+        });
+        *timeout.borrow_mut() = Some(source);
+    };
+    reset_timeout();
+
+    // the legacy controller receives every event which makes it possible to reset the timeout on any input
+    let event_controller = EventControllerLegacy::new();
+    event_controller.set_propagation_phase(gtk4::PropagationPhase::Capture);
+    event_controller.connect_event(move |_, _| {
+        reset_timeout();
+        gtk4::glib::Propagation::Proceed
+    });
+    window.add_controller(event_controller);
+}
+
+/// Close the picker as cancelled when the window loses the keyboard focus
+///
+/// the check is deferred until the focus settled such that hiding the window for the region selection and focus
+/// moving between widgets of the window don't close the picker
+fn add_close_on_focus_loss(window: &ApplicationWindow) {
+    let event_controller = EventControllerFocus::new();
+    event_controller.connect_leave(clone!(
+        #[weak]
+        window,
+        move |_| {
+            glib::idle_add_local_once(clone!(
+                #[weak]
+                window,
+                move || {
+                    if window.is_visible() && !window.is_active() {
+                        log::info!("exiting: window lost the focus");
+                        exit(1);
+                    }
+                }
+            ));
+        }
+    ));
+    window.add_controller(event_controller);
+}
+
+/// Cycle the notebook pages using the configured key, holding shift cycles the pages backwards
+///
+/// the controller runs in the capture phase such that the key doesn't additionally move the focus
+fn add_page_cycling(window: &ApplicationWindow, notebook: &Notebook, accelerator: &str) {
+    if accelerator.is_empty() {
+        return;
+    }
+    let Some((cycle_key, cycle_modifiers)) = gtk4::accelerator_parse(accelerator) else {
+        return log::error!("unable to parse page cycling key {accelerator}");
+    };
+    let cycle_modifiers = cycle_modifiers.difference(ModifierType::SHIFT_MASK);
+
+    let event_controller = EventControllerKey::new();
+    event_controller.set_propagation_phase(gtk4::PropagationPhase::Capture);
+    event_controller.connect_key_pressed(clone!(
+        #[weak]
+        notebook,
+        #[upgrade_or]
+        Propagation::Proceed,
+        move |_, key, _, state| {
+            // shift turns tab into left tab, therefore both are treated as the same key
+            let key = if key == Key::ISO_Left_Tab { Key::Tab } else { key.to_lower() };
+            let modifiers = state & gtk4::accelerator_get_default_mod_mask();
+            if key != cycle_key.to_lower() || modifiers.difference(ModifierType::SHIFT_MASK) != cycle_modifiers {
+                return Propagation::Proceed;
+            }
+            let Some(current) = notebook.current_page() else {
+                return Propagation::Proceed;
+            };
+            let backwards = modifiers.contains(ModifierType::SHIFT_MASK);
+            notebook.set_current_page(Some(cycle_page(current, notebook.n_pages(), backwards)));
+            Propagation::Stop
+        }
+    ));
+    window.add_controller(event_controller);
+}
+
+/// get the page after (or before) the current page, wrapping around at both ends
+fn cycle_page(current: u32, pages: u32, backwards: bool) -> u32 {
+    if pages == 0 {
+        current
+    } else if backwards {
+        (current + pages - 1) % pages
+    } else {
+        (current + 1) % pages
+    }
+}
+
+/// Build the search entry which filters all pages at once and selects a page with matches
+///
+/// the page which was selected before searching is restored once the search is cleared
+fn build_search_entry(notebook: &Notebook, filters: Vec<(u32, PageFilter)>, config: &Config) -> SearchEntry {
+    let entry = SearchEntry::builder().css_classes([config.classes.search_entry.as_str()]).build();
+    let previous_page = Cell::new(None);
+    entry.connect_search_changed(clone!(
+        #[weak]
+        notebook,
+        move |entry| {
+            let query = entry.text();
+            let matching = filters.iter().filter_map(|(page, filter)| filter(&query).then_some(*page)).collect::<Vec<_>>();
+            if query.is_empty() {
+                if let Some(page) = previous_page.take() {
+                    notebook.set_current_page(Some(page));
+                }
+                return;
+            }
+            if previous_page.get().is_none() {
+                previous_page.set(notebook.current_page());
+            }
+            if !notebook.current_page().is_some_and(|page| matching.contains(&page))
+                && let Some(page) = matching.first()
+            {
+                notebook.set_current_page(Some(*page));
+            }
+        }
+    ));
+    entry
+}
+
 fn build_restore_checkbox(restore_token: Rc<RefCell<bool>>, config: &Config) -> impl IsA<Widget> {
     let button = CheckButton::builder()
         .css_classes([config.classes.restore_button.as_str()])
@@ -221,3 +885,45 @@ fn build_restore_checkbox(restore_token: Rc<RefCell<bool>>, config: &Config) ->
 
     button
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_selection_window() {
+        assert_eq!(format_selection(false, "window:42"), "[SELECTION]/window:42");
+        assert_eq!(format_selection(true, "window:42"), "[SELECTION]r/window:42");
+    }
+
+    #[test]
+    fn format_selection_screen() {
+        assert_eq!(format_selection(false, "screen:DP-1"), "[SELECTION]/screen:DP-1");
+        assert_eq!(format_selection(true, "screen:DP-1"), "[SELECTION]r/screen:DP-1");
+    }
+
+    #[test]
+    fn format_selection_region() {
+        assert_eq!(format_selection(false, "region:DP-1@10,20,300,400"), "[SELECTION]/region:DP-1@10,20,300,400");
+        assert_eq!(format_selection(true, "region:DP-1@10,20,300,400"), "[SELECTION]r/region:DP-1@10,20,300,400");
+    }
+
+    #[test]
+    fn toggle_selection_adds_and_removes() {
+        let mut selected = Vec::new();
+        toggle_selection(&mut selected, String::from("window:42"));
+        toggle_selection(&mut selected, String::from("screen:DP-1"));
+        assert_eq!(selected, ["window:42", "screen:DP-1"]);
+        toggle_selection(&mut selected, String::from("window:42"));
+        assert_eq!(selected, ["screen:DP-1"]);
+    }
+
+    #[test]
+    fn cycle_page_wraps_around() {
+        assert_eq!(cycle_page(0, 3, false), 1);
+        assert_eq!(cycle_page(2, 3, false), 0);
+        assert_eq!(cycle_page(0, 3, true), 2);
+        assert_eq!(cycle_page(1, 3, true), 0);
+        assert_eq!(cycle_page(0, 1, false), 0);
+    }
+}
@@ -20,6 +20,18 @@ pub struct Cli {
     /// Alternative path to store logs
     pub logs: String,
 
+    #[arg(global = true, long)]
+    /// Append to the log file instead of truncating it on launch
+    pub append_logs: bool,
+
+    #[arg(global = true, long, value_name = "BYTES", requires = "append_logs")]
+    /// Rotate the log file on launch once it exceeds this size
+    pub max_log_size: Option<u64>,
+
+    #[arg(global = true, long, value_name = "COUNT", default_value_t = 3)]
+    /// Amount of rotated log files to keep
+    pub log_files: usize,
+
     #[arg(long, short)]
     /// Start the gtk inspector on application launch
     pub inspect: bool,
@@ -28,6 +40,56 @@ pub struct Cli {
     /// Allow the restore token by default
     pub allow_token_by_default: bool,
 
+    #[arg(long, value_name = "PATH")]
+    /// Read the window sharing list from a file (or stdin when `-`) instead of the `XDPH_WINDOW_SHARING_LIST` env
+    pub sharing_list: Option<String>,
+
+    #[arg(long)]
+    /// Print the toplevels and their matching hyprland clients without launching the picker
+    pub list: bool,
+
+    #[arg(long, conflicts_with = "list")]
+    /// Select the currently focused window without launching the picker
+    pub active_window: bool,
+
+    #[arg(long, conflicts_with_all = ["list", "active_window"])]
+    /// Run the region command and select the region without launching the picker
+    ///
+    /// exits with `1` without printing a selection when the region selection is cancelled
+    pub region_only: bool,
+
+    #[arg(long, conflicts_with_all = ["list", "active_window", "region_only"])]
+    /// Toggle the selection of cards instead of exiting and print all selections once confirmed
+    ///
+    /// every selection is printed on its own line
+    pub multi: bool,
+
+    #[arg(long, value_name = "FD")]
+    /// Write the selection to this inherited file descriptor instead of stdout
+    pub result_fd: Option<i32>,
+
+    #[arg(long, value_name = "PATH", conflicts_with = "result_fd")]
+    /// Write the selection to the unix socket at this path instead of stdout
+    pub result_socket: Option<String>,
+
+    #[arg(long, value_name = "FACTOR", value_parser = parse_scale)]
+    /// Debug override of the scale factor of all monitors in the outputs layout
+    ///
+    /// this only affects the positioning of the output cards (with `respect_output_scaling`), not the captures
+    pub scale: Option<f32>,
+
+    #[arg(long)]
+    /// Don't show the windows page
+    pub no_windows: bool,
+
+    #[arg(long)]
+    /// Don't show the outputs page
+    pub no_outputs: bool,
+
+    #[arg(long)]
+    /// Don't show the region page
+    pub no_region: bool,
+
     #[command(subcommand)]
     pub command: Option<Command>,
 }
@@ -37,6 +99,26 @@ pub enum Command {
     #[clap(hide = true)]
     /// Print the config schema
     Schema,
+    /// Inspect the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Load and validate the config file without launching the picker
+    ///
+    /// exits with `1` when the config is invalid and `2` when the config file is missing
+    Check,
+}
+
+fn parse_scale(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(scale) if scale.is_finite() && scale > 0.0 => Ok(scale),
+        _ => Err(format!("{value} is not a positive number")),
+    }
 }
 
 fn get_default_config_path() -> String {
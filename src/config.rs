@@ -2,7 +2,7 @@ use std::path::{Path, PathBuf};
 
 use log::{error, warn};
 use schemars::JsonSchema;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 
 #[derive(Deserialize, Debug, Clone, JsonSchema)]
 #[serde(default)]
@@ -18,8 +18,19 @@ pub struct Config {
     pub stylesheets: Vec<String>,
     /// hide the token restore checkbox and use the default value instead
     pub hide_token_restore: bool,
+    /// show a search entry above the notebook which filters the windows and outputs pages
+    pub show_search: bool,
+    /// show a progress bar above the notebook which fills up as the previews are captured
+    ///
+    /// the progress bar is hidden once all captures finished or failed
+    pub show_progress: bool,
     /// notebook page which is selected by default
     pub default_page: Page,
+    /// build the pages other than the default page once they're shown (or searched) for the first time
+    ///
+    /// **note**: the previews of a lazily built outputs page are captured whilst the picker is presented, therefore the
+    /// picker is visible in them
+    pub lazy_pages: bool,
     /// all config related to images
     pub image: ImageConfig,
     /// config for customizing widget css classes
@@ -36,20 +47,93 @@ pub struct Config {
 
 impl Config {
     pub fn new(path_str: &String) -> Self {
+        match Self::load(path_str) {
+            Ok(config) => config,
+            Err(ConfigError::Missing) => {
+                warn!("missing config file at {path_str}, using default instead!");
+                Self::default()
+            }
+            Err(err) => {
+                error!("{err} at {path_str}");
+                std::process::exit(1)
+            }
+        }
+    }
+
+    /// Read and deserialize the config file at the given path
+    pub fn load(path_str: &String) -> Result<Self, ConfigError> {
         let path = Path::new(path_str);
-        if path.exists() {
-            let str = std::fs::read_to_string(path).unwrap_or_default();
-            match serde_yaml::from_str(str.as_str()) {
-                Ok(config) => Self { path: path.to_path_buf(), ..config },
-                Err(err) => {
-                    error!("invalid config file at {path_str}: {err}");
-                    std::process::exit(1)
-                }
+        if !path.exists() {
+            return Err(ConfigError::Missing);
+        }
+        let str = std::fs::read_to_string(path).map_err(|err| ConfigError::Unreadable(err.to_string()))?;
+        match serde_yaml::from_str::<Self>(str.as_str()) {
+            Ok(config) => Ok(Self { path: path.to_path_buf(), ..config }),
+            Err(err) => Err(ConfigError::Invalid(err.to_string())),
+        }
+    }
+
+    /// Check the config for values which deserialize fine but can't be used by the picker
+    ///
+    /// all problems are collected instead of stopping at the first one
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.window.width <= 0 || self.window.height <= 0 {
+            errors.push(format!("window: size {}x{} must be positive", self.window.width, self.window.height));
+        }
+        if !self.window.cycle_pages_key.is_empty() && !is_valid_accelerator(&self.window.cycle_pages_key) {
+            errors.push(format!("window.cycle_pages_key: invalid key {}", self.window.cycle_pages_key));
+        }
+        if self.image.resize_size == 0 {
+            errors.push(String::from("image.resize_size: must be greater than zero"));
+        }
+        if self.windows.resize_size == Some(0) {
+            errors.push(String::from("windows.resize_size: must be greater than zero"));
+        }
+        if self.outputs.resize_size == Some(0) {
+            errors.push(String::from("outputs.resize_size: must be greater than zero"));
+        }
+        if self.image.widget_size <= 0 {
+            errors.push(String::from("image.widget_size: must be greater than zero"));
+        }
+        if let Some(placeholder) = &self.image.placeholder_path
+            && !self.resolve_path(placeholder).exists()
+        {
+            errors.push(format!("image.placeholder_path: {placeholder} does not exist"));
+        }
+        self.stylesheets.iter().filter(|path| !self.resolve_path(path).exists()).for_each(|path| {
+            errors.push(format!("stylesheets: {path} does not exist"));
+        });
+        if self.windows.clicks == 0 {
+            errors.push(String::from("windows.clicks: must be greater than zero"));
+        }
+        if self.windows.min_per_row == 0 {
+            errors.push(String::from("windows.min_per_row: must be greater than zero"));
+        }
+        if self.windows.min_per_row > self.windows.max_per_row {
+            errors.push(format!(
+                "windows.min_per_row: {} is greater than windows.max_per_row {}",
+                self.windows.min_per_row, self.windows.max_per_row
+            ));
+        }
+        if self.windows.thumbnail_aspect.is_some_and(|aspect| !aspect.is_finite() || aspect <= 0.0) {
+            errors.push(String::from("windows.thumbnail_aspect: must be greater than zero"));
+        }
+        self.windows.address_overrides.iter().for_each(|address_override| {
+            if let Err(err) = crate::util::parse_address(&address_override.address) {
+                errors.push(format!("windows.address_overrides: invalid address {}: {err}", address_override.address));
             }
-        } else {
-            warn!("missing config file at {path_str}, using default instead!");
-            Self::default()
+        });
+        if self.outputs.clicks == 0 {
+            errors.push(String::from("outputs.clicks: must be greater than zero"));
+        }
+        if self.outputs.max_concurrent_captures == 0 {
+            errors.push(String::from("outputs.max_concurrent_captures: must be greater than zero"));
         }
+        if self.region.command.trim().is_empty() {
+            errors.push(String::from("region.command: must not be empty"));
+        }
+        errors
     }
 
     pub fn path(&self) -> &PathBuf {
@@ -101,6 +185,49 @@ impl Config {
     }
 }
 
+/// modifier names accepted by `gtk4::accelerator_parse` (compared case insensitively)
+const ACCELERATOR_MODIFIERS: [&str; 11] =
+    ["shift", "shft", "control", "ctrl", "ctl", "primary", "alt", "mod1", "super", "hyper", "meta"];
+
+/// Check the syntax of a gtk accelerator (e.g. `<Control>Tab`) without initializing gtk
+///
+/// `gtk4::accelerator_parse` can't be used since it requires gtk to be initialized, which isn't the case when the
+/// config is checked from the command line. the key name itself is only checked for its characters
+fn is_valid_accelerator(accelerator: &str) -> bool {
+    let mut rest = accelerator.trim();
+    while let Some(modifier) = rest.strip_prefix('<') {
+        let Some((name, remaining)) = modifier.split_once('>') else {
+            return false;
+        };
+        if !ACCELERATOR_MODIFIERS.iter().any(|modifier| modifier.eq_ignore_ascii_case(name)) {
+            return false;
+        }
+        rest = remaining;
+    }
+    !rest.is_empty() && rest.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Reasons why a config file couldn't be loaded
+#[derive(Debug)]
+pub enum ConfigError {
+    /// there is no file at the path
+    Missing,
+    /// the file exists but couldn't be read
+    Unreadable(String),
+    /// the file isn't a valid config
+    Invalid(String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Missing => write!(f, "missing config file"),
+            ConfigError::Unreadable(err) => write!(f, "unreadable config file: {err}"),
+            ConfigError::Invalid(err) => write!(f, "invalid config file: {err}"),
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -113,7 +240,10 @@ impl Default for Config {
             outputs: OutputsConfig::default(),
             windows: WindowsConfig::default(),
             hide_token_restore: false,
+            show_search: false,
+            show_progress: false,
             default_page: Page::default(),
+            lazy_pages: false,
             debug: false,
         }
     }
@@ -127,11 +257,102 @@ pub struct WindowConfig {
     pub width: i32,
     /// target height of the application window
     pub height: i32,
+    /// close the picker as cancelled after this many seconds without any input
+    ///
+    /// `0` or no value keeps the picker open indefinitely
+    pub idle_timeout_s: Option<u32>,
+    /// close the picker as cancelled when the window loses the keyboard focus
+    ///
+    /// the window is hidden whilst a region is selected, losing the focus to the region command doesn't close it
+    pub close_on_focus_loss: bool,
+    /// layer shell layer the window is placed on
+    #[serde(deserialize_with = "default_on_invalid")]
+    pub layer: Layer,
+    /// keyboard interactivity of the layer shell window
+    #[serde(deserialize_with = "default_on_invalid")]
+    pub keyboard_mode: KeyboardMode,
+    /// key which selects the next notebook page, holding shift additionally selects the previous page
+    ///
+    /// the key is parsed as gtk accelerator (e.g. `<Control>Tab`), an empty value disables cycling the pages
+    pub cycle_pages_key: String,
 }
 
 impl Default for WindowConfig {
     fn default() -> Self {
-        Self { width: 1000, height: 500 }
+        Self {
+            width: 1000,
+            height: 500,
+            idle_timeout_s: None,
+            close_on_focus_loss: false,
+            layer: Layer::default(),
+            keyboard_mode: KeyboardMode::default(),
+            cycle_pages_key: String::from("<Control>Tab"),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, JsonSchema, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layer {
+    Background,
+    Bottom,
+    Top,
+    #[default]
+    Overlay,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, JsonSchema, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyboardMode {
+    None,
+    Exclusive,
+    #[default]
+    OnDemand,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, JsonSchema, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContentFit {
+    #[default]
+    Fill,
+    Contain,
+    Cover,
+    ScaleDown,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MouseButton {
+    #[default]
+    Left,
+    Middle,
+    Right,
+}
+
+impl MouseButton {
+    /// gdk button number of the mouse button
+    pub fn number(self) -> u32 {
+        match self {
+            MouseButton::Left => 1,
+            MouseButton::Middle => 2,
+            MouseButton::Right => 3,
+        }
+    }
+}
+
+/// Deserialize a value and fall back to its default with a warning when the value is invalid
+fn default_on_invalid<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    let value = serde_yaml::Value::deserialize(deserializer)?;
+    match T::deserialize(value.clone()) {
+        Ok(value) => Ok(value),
+        Err(err) => {
+            warn!("invalid config value {value:?}: {err}: using default instead");
+            Ok(T::default())
+        }
     }
 }
 
@@ -145,14 +366,68 @@ pub struct ImageConfig {
     pub resize_size: u32,
     /// target height of the widget containing the image
     pub widget_size: i32,
+    /// mirror the images after the monitor transformation was applied
+    pub flip: Flip,
+    /// delay in milliseconds between launching the window captures
+    ///
+    /// the n-th capture is launched after `n * capture_stagger_ms` milliseconds, `0` launches all captures at once
+    pub capture_stagger_ms: u64,
+    /// upscale window images which are smaller than `resize_size` such that all cards look uniform
+    pub upscale: bool,
+    /// path to an image which is shown on cards whose capture failed
+    ///
+    /// relative paths are resolved relative to the location of the config file. when unset the `image-missing` icon is shown
+    pub placeholder_path: Option<String>,
+    /// draw window previews with transparent regions onto a checkerboard pattern
+    ///
+    /// **note**: this only has an effect when the compositor captures the windows in a format with alpha channel
+    pub show_transparency: bool,
+    /// show a badge in the top left corner of every preview
+    ///
+    /// the badge contains the monitor name on output cards and the position on window cards
+    pub badge: bool,
+    /// interpolation which is used when the images are drawn at the size of their widget
+    ///
+    /// this is independent of the filter which is used when the images are internally resized
+    pub render_interpolation: RenderInterpolation,
 }
 
 impl Default for ImageConfig {
     fn default() -> Self {
-        Self { resize_size: 200, widget_size: 150 }
+        Self {
+            resize_size: 200,
+            widget_size: 150,
+            flip: Flip::default(),
+            capture_stagger_ms: 0,
+            upscale: false,
+            placeholder_path: None,
+            show_transparency: false,
+            badge: false,
+            render_interpolation: RenderInterpolation::default(),
+        }
     }
 }
 
+#[derive(Deserialize, Debug, Clone, Copy, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RenderInterpolation {
+    /// let gtk scale the images smoothly
+    #[default]
+    Smooth,
+    /// scale the images using nearest neighbor interpolation which keeps them crisp (e.g. for pixel art)
+    Nearest,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, JsonSchema, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Flip {
+    #[default]
+    None,
+    Horizontal,
+    Vertical,
+    Both,
+}
+
 #[derive(Deserialize, Debug, Clone, JsonSchema)]
 #[schemars(rename = "Classes config")]
 #[serde(default)]
@@ -163,10 +438,28 @@ pub struct ClassesConfig {
     pub image_card: String,
     /// class applied to the card holding the image and label when the image is being loaded
     pub image_card_loading: String,
+    /// class applied to the card holding the image and label when the window could only be matched by class and title
+    pub image_card_ambiguous: String,
+    /// class applied to the card holding the image and label after a click when more clicks are needed for the selection
+    pub image_card_armed: String,
+    /// class applied to the card holding the image and label whilst the pointer is above it
+    pub image_card_hover: String,
+    /// class applied to the card holding the image and label while it's part of the selection in multi select mode
+    pub image_card_selected: String,
+    /// class applied to the card holding the image and label when the window is not mapped, hidden or on a special workspace
+    pub image_card_unmapped: String,
+    /// class applied to the card holding the image and label when the window is on a workspace which isn't shown
+    pub image_card_inactive_workspace: String,
+    /// class additionally applied to the cards of the windows page in the list layout
+    pub image_card_row: String,
     /// class applied to the image widget
     pub image: String,
     /// class applied to the image label widget
     pub image_label: String,
+    /// class applied to the secondary labels of the window cards (e.g. the class in the list layout)
+    pub image_sublabel: String,
+    /// class applied to the badge overlaid onto the image
+    pub image_badge: String,
     /// class applied to the notebook widget
     pub notebook: String,
     /// class applied to the label of the notebook tabs
@@ -177,6 +470,14 @@ pub struct ClassesConfig {
     pub region_button: String,
     /// class applied to the button containing the session restore checkbox and label
     pub restore_button: String,
+    /// class applied to the button which selects all outputs
+    pub all_screens_button: String,
+    /// class applied to the search entry
+    pub search_entry: String,
+    /// class applied to the capture progress bar
+    pub progress_bar: String,
+    /// class applied to the button which confirms the selection in multi select mode
+    pub confirm_button: String,
 }
 
 impl Default for ClassesConfig {
@@ -185,13 +486,26 @@ impl Default for ClassesConfig {
             window: String::from("window"),
             image_card: String::from("card"),
             image_card_loading: String::from("card-loading"),
+            image_card_ambiguous: String::from("card-ambiguous"),
+            image_card_armed: String::from("card-armed"),
+            image_card_hover: String::from("card-hover"),
+            image_card_selected: String::from("card-selected"),
+            image_card_unmapped: String::from("card-unmapped"),
+            image_card_inactive_workspace: String::from("card-inactive-workspace"),
+            image_card_row: String::from("card-row"),
             image: String::from("image"),
             image_label: String::from("image-label"),
+            image_sublabel: String::from("image-sublabel"),
+            image_badge: String::from("image-badge"),
             notebook: String::from("notebook"),
             tab_label: String::from("tab-label"),
             notebook_page: String::from("page"),
             region_button: String::from("region-button"),
             restore_button: String::from("restore-button"),
+            all_screens_button: String::from("all-screens-button"),
+            search_entry: String::from("search-entry"),
+            progress_bar: String::from("progress-bar"),
+            confirm_button: String::from("confirm-button"),
         }
     }
 }
@@ -205,11 +519,13 @@ pub struct RegionConfig {
     /// the command should return a value in the following format:
     /// <output>@<x>,<y>,<w>,<h> (e.g. DP-3@2789,436,756,576)
     pub command: String,
+    /// additionally print the selected region in the `<x>,<y> <w>x<h>` geometry format used by grim to stderr
+    pub emit_grim_geometry: bool,
 }
 
 impl Default for RegionConfig {
     fn default() -> Self {
-        Self { command: String::from("slurp -f '%o@%x,%y,%w,%h'") }
+        Self { command: String::from("slurp -f '%o@%x,%y,%w,%h'"), emit_grim_geometry: false }
     }
 }
 
@@ -225,15 +541,61 @@ pub struct OutputsConfig {
     pub spacing: u32,
     /// show the output name label
     pub show_label: bool,
+    /// include the current mode of the output (e.g. `2560x1440@144Hz`) in the label
+    pub show_mode: bool,
     /// size the output cards respectively to their scaling
     ///
     /// **note**: when having too weird of a layout this should probably be disabled
     pub respect_output_scaling: bool,
+    /// maximum number of outputs which are captured at the same time
+    ///
+    /// the output the cursor is on is always captured first
+    pub max_concurrent_captures: usize,
+    /// token printed when selecting an output where `{name}` is replaced with the name of the output
+    pub screen_token: String,
+    /// token printed when selecting all outputs at once
+    ///
+    /// when set, a button for selecting all outputs is shown below the output layout
+    pub all_screens_token: Option<String>,
+    /// capture the outputs before presenting the picker such that it's not visible in the output previews
+    ///
+    /// **note**: this delays the picker until all outputs are captured (at most two seconds)
+    pub capture_before_present: bool,
+    /// scale factor which overrides the scale of all monitors when laying out the output cards
+    ///
+    /// this is only set by the `--scale` debug flag
+    #[serde(skip_deserializing)]
+    #[schemars(skip)]
+    pub scale_override: Option<f32>,
+    /// capture only the output the cursor is on right away and the other outputs once the outputs page is shown
+    ///
+    /// **note**: the deferred captures are taken whilst the picker is presented, therefore it's visible in their previews
+    pub defer_captures: bool,
+    /// how the output capture is fitted into its card
+    ///
+    /// `fill` stretches the capture to the card whereas `contain` keeps the aspect ratio and letterboxes it
+    pub content_fit: ContentFit,
+    /// internally downscale the output images to this height instead of `image.resize_size`
+    pub resize_size: Option<u32>,
 }
 
 impl Default for OutputsConfig {
     fn default() -> Self {
-        Self { spacing: 6, clicks: 2, show_label: false, respect_output_scaling: true }
+        Self {
+            spacing: 6,
+            clicks: 2,
+            show_label: false,
+            show_mode: false,
+            respect_output_scaling: true,
+            max_concurrent_captures: 2,
+            screen_token: String::from("screen:{name}"),
+            all_screens_token: None,
+            capture_before_present: true,
+            defer_captures: false,
+            scale_override: None,
+            content_fit: ContentFit::default(),
+            resize_size: None,
+        }
     }
 }
 
@@ -247,17 +609,145 @@ pub struct WindowsConfig {
     pub max_per_row: u32,
     /// number of clicks to trigger selection
     pub clicks: u32,
+    /// mouse button which selects a window card
+    ///
+    /// clicks with other buttons are ignored (e.g. to reserve the right button for a context menu)
+    pub select_button: MouseButton,
     /// spacing in pixels between the window cards
     pub spacing: u32,
+    /// inset in pixels which is cropped from the edges of the window captures (e.g. to remove decorations or shadows)
+    ///
+    /// either a single value for all edges or an object with `top`, `right`, `bottom` and `left`
+    ///
+    /// **note**: the toplevel export protocol doesn't allow controlling whether decorations are included in captures
+    pub decoration_inset: Inset,
+    /// classes of toplevels which aren't shown in the picker
+    ///
+    /// by default this contains known shell surfaces (wallpapers, lockscreens) which may leak into the sharing list
+    pub exclude_classes: Vec<String>,
+    /// hide toplevels which have neither a class nor a title
+    pub exclude_untitled: bool,
+    /// label of the cards whose toplevel has an empty title
+    ///
+    /// by default the class of the toplevel is shown instead
+    pub untitled_label: Option<String>,
+    /// hyprland addresses which are used for toplevels without a window address instead of matching by class and title
+    ///
+    /// this can be used to pick the correct window when multiple windows have the same class and title
+    pub address_overrides: Vec<AddressOverride>,
+    /// hide windows which are not mapped, hidden or on a special workspace instead of only marking them
+    pub only_mapped: bool,
+    /// internally downscale the window images to this height instead of `image.resize_size`
+    pub resize_size: Option<u32>,
+    /// show the icon of the application instead of the placeholder when a window can't be captured
+    ///
+    /// this also shows the windows page when the compositor doesn't support capturing windows at all
+    pub icon_fallback: bool,
+    /// center crop the window images to this aspect ratio (width / height) such that all cards are uniform
+    ///
+    /// e.g. `1.777` for 16:9 thumbnails
+    pub thumbnail_aspect: Option<f64>,
+    /// order of the window cards
+    ///
+    /// `frequency` shows the windows whose class was selected most often first. the selections are only counted
+    /// whilst this is enabled and stored per class in a local file inside the state directory
+    #[serde(deserialize_with = "default_on_invalid")]
+    pub sort_by: WindowSort,
+    /// arrangement of the window cards
+    ///
+    /// `list` shows a row with a small preview, the title and the class per window which suits many open windows
+    #[serde(deserialize_with = "default_on_invalid")]
+    pub layout: WindowLayout,
 }
 
 impl Default for WindowsConfig {
     fn default() -> Self {
-        Self { min_per_row: 3, max_per_row: 4, clicks: 2, spacing: 12 }
+        Self {
+            min_per_row: 3,
+            max_per_row: 4,
+            clicks: 2,
+            select_button: MouseButton::default(),
+            spacing: 12,
+            decoration_inset: Inset::default(),
+            exclude_classes: ["hyprpaper", "hyprlock", "swaybg", "swww-daemon", "mpvpaper"].map(String::from).to_vec(),
+            exclude_untitled: false,
+            untitled_label: None,
+            address_overrides: Vec::new(),
+            only_mapped: false,
+            resize_size: None,
+            icon_fallback: false,
+            thumbnail_aspect: None,
+            sort_by: WindowSort::default(),
+            layout: WindowLayout::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WindowLayout {
+    /// cards with a large preview above the title arranged in a grid
+    #[default]
+    Grid,
+    /// rows with a small preview next to the title and class arranged in a list
+    List,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WindowSort {
+    /// keep the order of the sharing list
+    #[default]
+    Default,
+    /// show the windows whose class was selected most often first
+    Frequency,
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct AddressOverride {
+    /// class of the toplevel
+    pub class: String,
+    /// title of the toplevel
+    pub title: String,
+    /// hyprland address of the window (e.g. `0x5713073a6a20`)
+    pub address: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, JsonSchema)]
+#[serde(untagged)]
+pub enum Inset {
+    /// same inset for all edges
+    Uniform(u32),
+    /// inset per edge
+    Edges {
+        #[serde(default)]
+        top: u32,
+        #[serde(default)]
+        right: u32,
+        #[serde(default)]
+        bottom: u32,
+        #[serde(default)]
+        left: u32,
+    },
+}
+
+impl Inset {
+    /// get the inset of the `(top, right, bottom, left)` edges
+    pub fn edges(&self) -> (u32, u32, u32, u32) {
+        match *self {
+            Inset::Uniform(inset) => (inset, inset, inset, inset),
+            Inset::Edges { top, right, bottom, left } => (top, right, bottom, left),
+        }
+    }
+}
+
+impl Default for Inset {
+    fn default() -> Self {
+        Self::Uniform(0)
     }
 }
 
-#[derive(Deserialize, Debug, Clone, JsonSchema, Default)]
+#[derive(Deserialize, Debug, Clone, Copy, JsonSchema, Default, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum Page {
     #[default]
@@ -265,3 +755,51 @@ pub enum Page {
     Outputs,
     Region,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_accepts_default_config() {
+        assert_eq!(Config::default().validate(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn validate_collects_all_errors() {
+        let mut config = Config::default();
+        config.window.width = 0;
+        config.windows.clicks = 0;
+        config.windows.min_per_row = 5;
+        config.region.command = String::from(" ");
+        assert_eq!(
+            config.validate(),
+            vec![
+                String::from("window: size 0x500 must be positive"),
+                String::from("windows.clicks: must be greater than zero"),
+                String::from("windows.min_per_row: 5 is greater than windows.max_per_row 4"),
+                String::from("region.command: must not be empty"),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_checks_cycle_pages_key() {
+        let mut config = Config::default();
+        config.window.cycle_pages_key = String::from("<Control><Hyperr>Tab");
+        assert_eq!(config.validate(), vec![String::from("window.cycle_pages_key: invalid key <Control><Hyperr>Tab")]);
+        config.window.cycle_pages_key = String::new();
+        assert!(config.validate().is_empty());
+    }
+
+    #[test]
+    fn accelerator_syntax() {
+        assert!(is_valid_accelerator("<Control>Tab"));
+        assert!(is_valid_accelerator("<ctrl><Shift>Page_Down"));
+        assert!(is_valid_accelerator("F5"));
+        assert!(!is_valid_accelerator("<Control>"));
+        assert!(!is_valid_accelerator("<Control Tab"));
+        assert!(!is_valid_accelerator("<Foo>Tab"));
+        assert!(!is_valid_accelerator("Control+Tab"));
+    }
+}
@@ -1,23 +1,38 @@
-use gtk4::gdk_pixbuf::Pixbuf;
-use hyprland_preview_share_picker_lib::image::{Image, ImageKind};
+use hyprland_preview_share_picker_lib::image::Image;
+
+use crate::config::{Flip, Inset};
 
 pub trait ImageExt {
-    /// turn the image into a gdk pixbuf which can directly be displayed inside a gtk image
-    fn into_pixbuf(self) -> Result<Pixbuf, Box<dyn std::error::Error>>;
+    /// mirror the image as configured
+    fn apply_flip(&mut self, flip: Flip);
+    /// crop the configured inset from the edges of the image
+    ///
+    /// if the inset is bigger than the image nothing happens
+    fn apply_inset(&mut self, inset: Inset);
 }
 
 impl ImageExt for Image {
-    fn into_pixbuf(self) -> Result<Pixbuf, Box<dyn std::error::Error>> {
-        let rgb_image = match self.into_rgb()?.buffer {
-            ImageKind::Xrgb(_) => unreachable!("the image just got converted to rgb"),
-            ImageKind::Rgb(image_buffer) => image_buffer,
-        };
-
-        let height = rgb_image.height() as i32;
-        let width = rgb_image.width() as i32;
+    fn apply_flip(&mut self, flip: Flip) {
+        match flip {
+            Flip::None => {}
+            Flip::Horizontal => self.flip_horizontal(),
+            Flip::Vertical => self.flip_vertical(),
+            Flip::Both => {
+                self.flip_horizontal();
+                self.flip_vertical();
+            }
+        }
+    }
 
-        let bytes = gtk4::glib::Bytes::from(&rgb_image.into_vec());
-        let pixbuf = Pixbuf::from_bytes(&bytes, gtk4::gdk_pixbuf::Colorspace::Rgb, false, 8, width, height, width * 3);
-        Ok(pixbuf)
+    fn apply_inset(&mut self, inset: Inset) {
+        let (top, right, bottom, left) = inset.edges();
+        let (width, height) = self.dimensions();
+        if top + bottom == 0 && left + right == 0 {
+            return;
+        }
+        if left + right >= width || top + bottom >= height {
+            return log::warn!("inset {inset:?} is bigger than image with dimensions {width}x{height}");
+        }
+        self.crop(left, top, width - left - right, height - top - bottom);
     }
 }
@@ -0,0 +1,59 @@
+use hyprland::data::{Client, Monitor};
+use hyprland_preview_share_picker_lib::toplevel::Toplevel;
+
+use crate::util::{ClientMatch, client_handle, get_clients, get_monitors, match_client};
+
+/// Print a table of all toplevels and whether they could be matched to a hyprland client
+///
+/// this performs the same matching as the windows view without launching the gtk application
+pub fn print_toplevels(toplevels: &[Toplevel]) -> Result<(), String> {
+    let clients = get_clients()?;
+    let monitors = get_monitors()?;
+
+    println!("{:<8} {:<16} {:<24} {:<32} {:<16} STATUS", "ID", "ADDRESS", "CLASS", "TITLE", "CLIENT");
+    toplevels.iter().for_each(|toplevel| {
+        let address = toplevel.window_address.map(|address| format!("{address:x}")).unwrap_or(String::from("-"));
+        let (client, status) = match_status(toplevel, &clients, &monitors);
+        println!(
+            "{:<8} {:<16} {:<24} {:<32} {:<16} {status}",
+            toplevel.id,
+            address,
+            truncate(&toplevel.class, 24),
+            truncate(&toplevel.title, 32),
+            client.map(|c| c.address.to_string()).unwrap_or(String::from("-")),
+        );
+    });
+
+    Ok(())
+}
+
+/// Describe whether a toplevel matched a hyprland client and why not
+fn match_status<'a>(toplevel: &Toplevel, clients: &'a [Client], monitors: &[Monitor]) -> (Option<&'a Client>, String) {
+    let Some(client_match) = match_client(toplevel, clients) else {
+        return (None, String::from("no hyprland client with matching window address or class and title"));
+    };
+    let client = client_match.client();
+    if !monitors.iter().any(|m| m.id == client.monitor) {
+        return (Some(client), format!("hyprland monitor {} of client does not exist", client.monitor));
+    }
+    if let Err(err) = client_handle(client) {
+        return (Some(client), format!("unable to convert client address to u64: {err}"));
+    }
+    match client_match {
+        ClientMatch::Address(_) => (Some(client), String::from("ok")),
+        ClientMatch::ClassTitle(_) => (Some(client), String::from("ok (ambiguous: matched by class and title)")),
+        ClientMatch::StaleAddress(_) => {
+            (Some(client), String::from("ok (window address not found: matched by class and title)"))
+        }
+    }
+}
+
+fn truncate(str: &str, max: usize) -> String {
+    if str.chars().count() > max {
+        let mut truncated = str.chars().take(max - 1).collect::<String>();
+        truncated.push('…');
+        truncated
+    } else {
+        str.to_string()
+    }
+}
@@ -1,51 +1,84 @@
-use app::App;
+use app::{App, Pages};
 use clap::Parser;
 use cli::Cli;
-use config::Config;
+use config::{Config, ConfigError};
 use hyprland_preview_share_picker_lib::toplevel::Toplevel;
-use log::LevelFilter;
 use schemars::r#gen::SchemaSettings;
-use std::io::Write;
+use sink::SelectionSink;
+use std::{
+    fs::{File, OpenOptions},
+    io::Read,
+    os::unix::ffi::OsStringExt,
+    sync::Mutex,
+};
+use tracing::Level;
+use tracing_subscriber::fmt::time::ChronoUtc;
 
 mod app;
 mod cli;
 mod config;
 mod image;
+mod list;
+mod reconnect;
+mod sink;
+mod stats;
 mod util;
 mod views;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
-    let config = Config::new(&cli.config);
-    let log_file = Box::new(std::fs::File::create(cli.logs).expect("unable to create log file"));
-    env_logger::Builder::new()
-        .target(env_logger::Target::Pipe(log_file))
-        .filter(None, if cli.debug || config.debug { LevelFilter::Debug } else { LevelFilter::Info })
-        .format(|buf, record| {
-            let now = chrono::Utc::now();
-            writeln!(
-                buf,
-                "[{} {:<5} {}] {}",
-                now.format("%Y-%m-%dT%H:%M:%S%.3fZ"),
-                record.level(),
-                record.target(),
-                record.args()
-            )
-        })
+    if let Some(cli::Command::Config { command: cli::ConfigCommand::Check }) = cli.command {
+        check_config(&cli.config);
+    }
+    let mut config = Config::new(&cli.config);
+    config.outputs.scale_override = cli.scale;
+    let log_file = open_log_file(&cli).expect("unable to create log file");
+    // the lib and most call sites use the log crate which is bridged to tracing by the subscriber
+    tracing_subscriber::fmt()
+        .with_writer(Mutex::new(log_file))
+        .with_ansi(false)
+        .with_timer(ChronoUtc::new(String::from("%Y-%m-%dT%H:%M:%S%.3fZ")))
+        .with_max_level(if cli.debug || config.debug { Level::DEBUG } else { Level::INFO })
         .init();
     log::debug!("initialized logger");
 
     match cli.command {
         None => {
-            let toplevel_sharing_list = std::env::var("XDPH_WINDOW_SHARING_LIST").unwrap_or_default();
-            log::debug!("XDPH_WINDOW_SHARING_LIST = {toplevel_sharing_list}");
-            let toplevels = Toplevel::parse_list(&toplevel_sharing_list);
+            let toplevel_sharing_list = read_sharing_list(cli.sharing_list.as_deref());
+            log::debug!("XDPH_WINDOW_SHARING_LIST = {}", String::from_utf8_lossy(&toplevel_sharing_list));
+            let toplevels = Toplevel::parse_list_bytes(&toplevel_sharing_list).unwrap_or_else(|err| {
+                log::error!("unable to parse window sharing list: {err}");
+                Vec::new()
+            });
             log::debug!("using config: {config:#?}");
 
             log::debug!("got toplevels {toplevels:#?}");
+            let sink = SelectionSink::new(cli.result_fd, cli.result_socket.as_deref());
+
+            if cli.list {
+                return list::print_toplevels(&toplevels).map_err(|err| err.into());
+            }
+
+            if cli.active_window {
+                let selection = app::select_active_window(&toplevels, cli.allow_token_by_default)?;
+                return sink.emit(&selection).map_err(|err| format!("unable to write selection: {err}").into());
+            }
+
+            if cli.region_only {
+                let Some(selection) = app::select_region(&config, cli.allow_token_by_default)? else {
+                    log::info!("region selection was cancelled");
+                    std::process::exit(1)
+                };
+                return sink.emit(&selection).map_err(|err| format!("unable to write selection: {err}").into());
+            }
 
-            let app = App::build(cli.inspect, config, toplevels, cli.allow_token_by_default);
+            let pages = Pages { windows: !cli.no_windows, outputs: !cli.no_outputs, region: !cli.no_region };
+            if !pages.any() {
+                return Err("all pages are disabled: remove one of --no-windows, --no-outputs or --no-region".into());
+            }
+
+            let app = App::build(cli.inspect, config, toplevels, cli.allow_token_by_default, pages, cli.multi, sink);
             app.run();
         }
         Some(cli::Command::Schema) => {
@@ -53,7 +86,98 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let schema = generator.into_root_schema_for::<Config>();
             println!("{}", serde_json::to_string_pretty(&schema).expect("should be a valid schema"))
         }
+        Some(cli::Command::Config { .. }) => unreachable!("config commands are handled before loading the config"),
     }
 
     Ok(())
 }
+
+/// Load and validate the config at the given path, report all problems and exit
+///
+/// this runs before the logger is initialized and never connects to wayland or gtk
+fn check_config(path: &String) -> ! {
+    let config = match Config::load(path) {
+        Ok(config) => config,
+        Err(err @ ConfigError::Missing) => {
+            eprintln!("{err} at {path}");
+            std::process::exit(2)
+        }
+        Err(err) => {
+            eprintln!("{err} at {path}");
+            std::process::exit(1)
+        }
+    };
+    let errors = config.validate();
+    if errors.is_empty() {
+        println!("config at {path} is valid");
+        std::process::exit(0)
+    }
+    eprintln!("config at {path} has {} error(s):", errors.len());
+    errors.iter().for_each(|err| eprintln!("  {err}"));
+    std::process::exit(1)
+}
+
+/// Open the log file according to the cli arguments
+///
+/// when appending, the file is opened with `O_APPEND` which makes every write land at the end of the file.
+/// since each log line is written with a single write call, concurrent launches sharing the same log don't
+/// interleave their lines
+fn open_log_file(cli: &Cli) -> std::io::Result<File> {
+    if !cli.append_logs {
+        return File::create(&cli.logs);
+    }
+    if let Some(max_size) = cli.max_log_size
+        && std::fs::metadata(&cli.logs).is_ok_and(|metadata| metadata.len() > max_size)
+    {
+        rotate_log_files(&cli.logs, cli.log_files)?;
+    }
+    OpenOptions::new().create(true).append(true).open(&cli.logs)
+}
+
+/// Shift `path.1` through `path.{keep - 1}` up by one and move `path` to `path.1`
+///
+/// the oldest file is overwritten by the rename. with `keep` set to zero the log is removed instead
+fn rotate_log_files(path: &str, keep: usize) -> std::io::Result<()> {
+    if keep == 0 {
+        return std::fs::remove_file(path);
+    }
+    for index in (1..keep).rev() {
+        let from = format!("{path}.{index}");
+        if std::fs::exists(&from)? {
+            std::fs::rename(&from, format!("{path}.{}", index + 1))?;
+        }
+    }
+    // another launch might have rotated the file in the meantime
+    match std::fs::rename(path, format!("{path}.1")) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Read the window sharing list from the provided source
+///
+/// `-` reads the list from stdin, any other value is treated as path to a file. When no source is
+/// provided the list is read from the `XDPH_WINDOW_SHARING_LIST` env. The list is returned as raw bytes
+/// such that invalid utf-8 is reported by the parser instead of dropping the whole list
+fn read_sharing_list(source: Option<&str>) -> Vec<u8> {
+    let mut list = match source {
+        Some("-") => {
+            let mut list = Vec::new();
+            if let Err(err) = std::io::stdin().read_to_end(&mut list) {
+                log::error!("unable to read window sharing list from stdin: {err}");
+            }
+            list
+        }
+        Some(path) => std::fs::read(path).unwrap_or_else(|err| {
+            log::error!("unable to read window sharing list from {path}: {err}");
+            Vec::new()
+        }),
+        None => {
+            return std::env::var_os("XDPH_WINDOW_SHARING_LIST").map(|list| list.into_vec()).unwrap_or_default();
+        }
+    };
+    while list.last() == Some(&b'\n') {
+        list.pop();
+    }
+    list
+}
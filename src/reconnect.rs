@@ -0,0 +1,36 @@
+use std::sync::{Arc, Mutex};
+
+use hyprland_preview_share_picker_lib::error::Error;
+use wayland_client::Connection;
+
+/// Protocol manager which is rebuilt from a new wayland connection after the connection was lost
+///
+/// the connection is only re-established once and the result is shared by all captures, therefore a compositor which
+/// is gone for good fails every capture exactly once more instead of reconnecting in a loop
+pub struct Reconnect<T> {
+    build: fn(&Connection) -> Result<T, Error>,
+    manager: Mutex<Option<Result<Arc<T>, String>>>,
+}
+
+impl<T> Reconnect<T> {
+    pub fn new(build: fn(&Connection) -> Result<T, Error>) -> Arc<Self> {
+        Arc::new(Self { build, manager: Mutex::new(None) })
+    }
+
+    /// get the manager of the new connection
+    ///
+    /// connects to the wayland server on the first call and blocks until the manager is set up
+    pub fn manager(&self) -> Result<Arc<T>, String> {
+        let mut manager = self.manager.lock().expect("lock should not be poisoned");
+        manager
+            .get_or_insert_with(|| {
+                log::warn!("wayland connection was lost: reconnecting to wayland server");
+                let connection =
+                    Connection::connect_to_env().map_err(|err| format!("unable to reconnect to wayland server: {err}"))?;
+                (self.build)(&connection)
+                    .map(Arc::new)
+                    .map_err(|err| format!("unable to set up protocol manager after reconnect: {err}"))
+            })
+            .clone()
+    }
+}
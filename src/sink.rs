@@ -0,0 +1,58 @@
+use std::{
+    fs::File,
+    io::Write,
+    net::Shutdown,
+    os::{
+        fd::{FromRawFd, RawFd},
+        unix::net::UnixStream,
+    },
+    path::PathBuf,
+};
+
+/// Destination the selection is written to
+///
+/// the selection is written to stdout unless a file descriptor or unix socket was provided on the command line
+#[derive(Debug, Clone, Default)]
+pub enum SelectionSink {
+    #[default]
+    Stdout,
+    /// file descriptor inherited from the parent process (e.g. the write end of a pipe)
+    Fd(RawFd),
+    /// path of a unix socket which is connected to for writing the selection
+    Socket(PathBuf),
+}
+
+impl SelectionSink {
+    pub fn new(fd: Option<RawFd>, socket: Option<&str>) -> Self {
+        match (fd, socket) {
+            (Some(fd), _) => Self::Fd(fd),
+            (None, Some(path)) => Self::Socket(PathBuf::from(path)),
+            (None, None) => Self::Stdout,
+        }
+    }
+
+    /// write the selection followed by a newline and flush it
+    ///
+    /// the file descriptor and socket are closed afterwards which signals the end of the selection to the reader
+    pub fn emit(&self, selection: &str) -> std::io::Result<()> {
+        match self {
+            SelectionSink::Stdout => {
+                let mut stdout = std::io::stdout().lock();
+                writeln!(stdout, "{selection}")?;
+                stdout.flush()
+            }
+            SelectionSink::Fd(fd) => {
+                // SAFETY: the file descriptor was passed to the process for writing the selection and isn't used elsewhere
+                let mut file = unsafe { File::from_raw_fd(*fd) };
+                writeln!(file, "{selection}")?;
+                file.flush()
+            }
+            SelectionSink::Socket(path) => {
+                let mut stream = UnixStream::connect(path)?;
+                writeln!(stream, "{selection}")?;
+                stream.flush()?;
+                stream.shutdown(Shutdown::Write)
+            }
+        }
+    }
+}
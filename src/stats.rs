@@ -0,0 +1,94 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use hyprland_preview_share_picker_lib::toplevel::Toplevel;
+use serde::{Deserialize, Serialize};
+
+/// Name of the directory inside the state directory which holds the stats file
+const STATE_DIR: &str = "hyprland-preview-share-picker";
+const STATS_FILE: &str = "window-stats.json";
+
+/// How often windows were selected by their class
+///
+/// only the classes and their counts are stored in a file in the local state directory (`~/.local/state`)
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct WindowStats {
+    classes: HashMap<String, u64>,
+}
+
+impl WindowStats {
+    fn path() -> Option<PathBuf> {
+        dirs::state_dir().map(|dir| dir.join(STATE_DIR).join(STATS_FILE))
+    }
+
+    /// load the stats from the state directory
+    ///
+    /// a missing or invalid stats file results in empty stats
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        let Ok(content) = fs::read_to_string(&path) else {
+            log::debug!("no window stats found at {}", path.display());
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|err| {
+            log::warn!("ignoring invalid window stats at {}: {err}", path.display());
+            Self::default()
+        })
+    }
+
+    /// write the stats to the state directory
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or(String::from("unable to find the state directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| format!("unable to create {}: {err}", dir.display()))?;
+        }
+        let content = serde_json::to_string(self).map_err(|err| format!("unable to serialize window stats: {err}"))?;
+        fs::write(&path, content).map_err(|err| format!("unable to write window stats to {}: {err}", path.display()))
+    }
+
+    /// get how often windows with the class were selected
+    pub fn count(&self, class: &str) -> u64 {
+        self.classes.get(class).copied().unwrap_or(0)
+    }
+
+    /// count the classes of the toplevels of all selected windows
+    ///
+    /// selections of other pages and of unknown toplevels are ignored
+    pub fn record(&mut self, parameters: &[String], toplevels: &[Toplevel]) {
+        parameters
+            .iter()
+            .filter_map(|parameter| parameter.strip_prefix("window:")?.parse::<u64>().ok())
+            .filter_map(|id| toplevels.iter().find(|toplevel| toplevel.id == id))
+            .filter(|toplevel| !toplevel.class.is_empty())
+            .for_each(|toplevel| *self.classes.entry(toplevel.class.clone()).or_default() += 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toplevel(id: u64, class: &str) -> Toplevel {
+        Toplevel { id, class: class.to_string(), title: String::new(), window_address: None }
+    }
+
+    #[test]
+    fn record_counts_selected_window_classes() {
+        let toplevels = [toplevel(1, "kitty"), toplevel(2, "firefox"), toplevel(3, "")];
+        let mut stats = WindowStats::default();
+        stats.record(&[String::from("window:1"), String::from("window:3"), String::from("screen:DP-1")], &toplevels);
+        stats.record(&[String::from("window:1"), String::from("window:42")], &toplevels);
+        assert_eq!(stats.count("kitty"), 2);
+        assert_eq!(stats.count("firefox"), 0);
+        assert_eq!(stats.count(""), 0);
+    }
+
+    #[test]
+    fn stats_round_trip_through_json() {
+        let mut stats = WindowStats::default();
+        stats.record(&[String::from("window:1")], &[toplevel(1, "kitty")]);
+        let content = serde_json::to_string(&stats).expect("stats should be serializable");
+        assert_eq!(serde_json::from_str::<WindowStats>(&content).expect("stats should be deserializable"), stats);
+    }
+}
@@ -1,25 +1,15 @@
-use hyprland::data::{Client, Monitor};
-
-pub trait MonitorTransformExt {
-    fn apply_transform(&mut self);
-}
-
-impl MonitorTransformExt for Monitor {
-    fn apply_transform(&mut self) {
-        match self.transform {
-            hyprland::data::Transforms::Normal
-            | hyprland::data::Transforms::Normal180
-            | hyprland::data::Transforms::Flipped
-            | hyprland::data::Transforms::Flipped180 => {}
-            hyprland::data::Transforms::Normal90
-            | hyprland::data::Transforms::Normal270
-            | hyprland::data::Transforms::Flipped90
-            | hyprland::data::Transforms::Flipped270 => {
-                std::mem::swap(&mut self.height, &mut self.width);
-            }
-        }
-    }
-}
+use std::num::ParseIntError;
+
+use hyprland::{
+    data::{Client, Clients, Monitor, Monitors},
+    shared::{HyprData, HyprDataActiveOptional},
+};
+use hyprland_preview_share_picker_lib::toplevel::Toplevel;
+
+use crate::config::AddressOverride;
+
+/// label of toplevels with neither a title nor a class when no untitled label is configured
+const UNTITLED_LABEL: &str = "(untitled)";
 
 pub trait ClientExt {
     fn sanitize(&mut self);
@@ -35,3 +25,307 @@ impl ClientExt for Client {
 fn sanitize_string(target: &str) -> String {
     target.replace(['\'', '\"', '$', '`'], " ").replace(">]", ">")
 }
+
+/// Get all hyprland clients with sanitized class and title
+pub fn get_clients() -> Result<Vec<Client>, String> {
+    Clients::get()
+        .map(|clients| {
+            clients
+                .into_iter()
+                .map(|mut client| {
+                    client.sanitize();
+                    client
+                })
+                .collect::<Vec<_>>()
+        })
+        .map_err(|err| format!("unable to get clients from hyprland socket: {err}"))
+}
+
+/// Get the currently focused hyprland client with sanitized class and title
+pub fn get_active_client() -> Result<Option<Client>, String> {
+    Client::get_active()
+        .map(|client| {
+            client.map(|mut client| {
+                client.sanitize();
+                client
+            })
+        })
+        .map_err(|err| format!("unable to get active client from hyprland socket: {err}"))
+}
+
+/// Get all hyprland monitors
+pub fn get_monitors() -> Result<Vec<Monitor>, String> {
+    Monitors::get()
+        .map(|monitors| monitors.into_iter().collect::<Vec<_>>())
+        .map_err(|err| format!("unable to get monitors from hyprland socket: {err}"))
+}
+
+/// Find the hyprland client which belongs to a toplevel
+///
+/// the window address of the toplevel is preferred when present. otherwise the client is matched by class and title
+/// which is kindof bad since multiple windows could have the same class and title but afaik there is no clean
+/// way to get a hyprland window address for a wayland toplevel id
+pub fn match_client<'a>(toplevel: &Toplevel, clients: &'a [Client]) -> Option<ClientMatch<'a>> {
+    match_client_with_overrides(toplevel, clients, &[])
+}
+
+/// Find the hyprland client which belongs to a toplevel respecting the address overrides
+///
+/// the overrides are only used for toplevels without window address. overrides with an address which doesn't belong
+/// to any client are ignored
+pub fn match_client_with_overrides<'a>(
+    toplevel: &Toplevel,
+    clients: &'a [Client],
+    overrides: &[AddressOverride],
+) -> Option<ClientMatch<'a>> {
+    if toplevel.window_address.is_none()
+        && let Some(address_override) = overrides.iter().find(|o| o.class.eq(&toplevel.class) && o.title.eq(&toplevel.title))
+    {
+        match parse_address(&address_override.address) {
+            Ok(address) => match clients.iter().find(|c| client_handle(c).is_ok_and(|handle| handle == address)) {
+                Some(client) => return Some(ClientMatch::Address(client)),
+                None => log::warn!("address override {} does not belong to any client", address_override.address),
+            },
+            Err(err) => log::warn!("invalid address override {}: {err}", address_override.address),
+        }
+    }
+    if let Some(address) = toplevel.window_address
+        && let Some(client) = clients.iter().find(|c| client_handle(c).is_ok_and(|handle| handle == address))
+    {
+        return Some(ClientMatch::Address(client));
+    }
+    let client = clients.iter().find(|c| c.class.eq(&toplevel.class) && c.title.eq(&toplevel.title))?;
+    match toplevel.window_address {
+        Some(_) => Some(ClientMatch::StaleAddress(client)),
+        None => Some(ClientMatch::ClassTitle(client)),
+    }
+}
+
+/// Hyprland client matched to a toplevel by [`match_client`]
+#[derive(Debug, Clone, Copy)]
+pub enum ClientMatch<'a> {
+    /// the client was matched by the window address of the toplevel
+    Address(&'a Client),
+    /// the client was matched by class and title which is not guaranteed to be unique
+    ClassTitle(&'a Client),
+    /// the toplevel has a window address which doesn't belong to any client and the client was matched by class
+    /// and title instead
+    StaleAddress(&'a Client),
+}
+
+impl<'a> ClientMatch<'a> {
+    pub fn client(&self) -> &'a Client {
+        match self {
+            ClientMatch::Address(client) | ClientMatch::ClassTitle(client) | ClientMatch::StaleAddress(client) => client,
+        }
+    }
+
+    /// whether the client could be a different window with the same class and title
+    ///
+    /// only toplevels without window address are ambiguous, a stale address is an unexpected mismatch instead
+    pub fn is_ambiguous(&self) -> bool {
+        matches!(self, ClientMatch::ClassTitle(_))
+    }
+}
+
+/// Convert the hex address of a hyprland client (e.g. `0x5713073a6a20`) into a window handle
+pub fn client_handle(client: &Client) -> Result<u64, ParseIntError> {
+    parse_address(&format!("{}", client.address))
+}
+
+/// Parse a hex hyprland window address with an optional `0x` prefix
+pub fn parse_address(address: &str) -> Result<u64, ParseIntError> {
+    u64::from_str_radix(address.trim_start_matches("0x"), 16)
+}
+
+/// Get the name of the workspace of a client when the workspace isn't shown on any monitor
+///
+/// special workspaces (negative ids) are ignored since they're never active. without any monitors the workspace of the
+/// client can't be checked, therefore `None` is returned as well
+pub fn inactive_workspace<'a>(client: &'a Client, monitors: &[Monitor]) -> Option<&'a str> {
+    let active = monitors.iter().any(|monitor| monitor.active_workspace.id == client.workspace.id);
+    (client.workspace.id >= 0 && !monitors.is_empty() && !active).then_some(client.workspace.name.as_str())
+}
+
+/// Get the title which is shown on the card of a toplevel
+///
+/// toplevels with an empty (or whitespace only) title show the configured untitled label or their class instead
+pub fn display_title<'a>(toplevel: &'a Toplevel, untitled_label: Option<&'a str>) -> &'a str {
+    if !toplevel.title.trim().is_empty() {
+        &toplevel.title
+    } else if let Some(label) = untitled_label {
+        label
+    } else if !toplevel.class.trim().is_empty() {
+        &toplevel.class
+    } else {
+        UNTITLED_LABEL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(address: &str, class: &str, title: &str) -> Client {
+        serde_json::from_value(serde_json::json!({
+            "address": address,
+            "mapped": true,
+            "hidden": false,
+            "at": [0, 0],
+            "size": [1920, 1080],
+            "workspace": { "id": 1, "name": "1" },
+            "floating": false,
+            "pseudo": false,
+            "monitor": 0,
+            "class": class,
+            "title": title,
+            "initialClass": class,
+            "initialTitle": title,
+            "pid": 1,
+            "xwayland": false,
+            "pinned": false,
+            "fullscreen": 0,
+            "fullscreenClient": 0,
+            "grouped": [],
+            "tags": [],
+            "swallowing": "0x0",
+            "focusHistoryID": 0
+        }))
+        .expect("should be a valid client")
+    }
+
+    fn client_on_workspace(id: i32, name: &str) -> Client {
+        let mut client = client("0x1", "kitty", "~");
+        client.workspace.id = id;
+        client.workspace.name = name.to_string();
+        client
+    }
+
+    fn monitor(id: i128, active_workspace: i32) -> Monitor {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": format!("DP-{id}"),
+            "description": "",
+            "make": "",
+            "model": "",
+            "serial": "",
+            "width": 1920,
+            "height": 1080,
+            "refreshRate": 60.0,
+            "x": 0,
+            "y": 0,
+            "activeWorkspace": { "id": active_workspace, "name": active_workspace.to_string() },
+            "specialWorkspace": { "id": 0, "name": "" },
+            "reserved": [0, 0, 0, 0],
+            "scale": 1.0,
+            "transform": 0,
+            "focused": id == 0,
+            "dpmsStatus": true,
+            "vrr": false,
+            "activelyTearing": false,
+            "disabled": false,
+            "currentFormat": "XRGB8888",
+            "availableModes": []
+        }))
+        .expect("should be a valid monitor")
+    }
+
+    fn toplevel(class: &str, title: &str, window_address: Option<u64>) -> Toplevel {
+        Toplevel { id: 1, class: class.to_string(), title: title.to_string(), window_address }
+    }
+
+    fn address_override(class: &str, title: &str, address: &str) -> AddressOverride {
+        AddressOverride { class: class.to_string(), title: title.to_string(), address: address.to_string() }
+    }
+
+    #[test]
+    fn match_client_uses_address_override() {
+        let clients = [client("0xa1", "kitty", "~"), client("0xb2", "kitty", "~")];
+        let overrides = [address_override("kitty", "~", "0xb2")];
+        let matched =
+            match_client_with_overrides(&toplevel("kitty", "~", None), &clients, &overrides).expect("should match a client");
+        assert_eq!(client_handle(matched.client()), Ok(0xb2));
+        assert!(!matched.is_ambiguous());
+    }
+
+    #[test]
+    fn match_client_ignores_stale_address_override() {
+        let clients = [client("0xa1", "kitty", "~")];
+        let overrides = [address_override("kitty", "~", "0xb2")];
+        let matched =
+            match_client_with_overrides(&toplevel("kitty", "~", None), &clients, &overrides).expect("should match a client");
+        assert_eq!(client_handle(matched.client()), Ok(0xa1));
+        assert!(matched.is_ambiguous());
+    }
+
+    #[test]
+    fn match_client_prefers_window_address() {
+        let clients = [client("0xa1", "kitty", "~"), client("0xb2", "kitty", "~")];
+        let matched = match_client(&toplevel("kitty", "~", Some(0xb2)), &clients).expect("should match a client");
+        assert_eq!(client_handle(matched.client()), Ok(0xb2));
+        assert!(!matched.is_ambiguous());
+    }
+
+    #[test]
+    fn match_client_falls_back_to_class_and_title() {
+        let clients = [client("0xa1", "firefox", "Mozilla Firefox"), client("0xb2", "kitty", "~")];
+        let matched = match_client(&toplevel("kitty", "~", Some(0xc3)), &clients).expect("should match a client");
+        assert_eq!(client_handle(matched.client()), Ok(0xb2));
+        assert!(matches!(matched, ClientMatch::StaleAddress(_)));
+        assert!(!matched.is_ambiguous());
+        let matched = match_client(&toplevel("kitty", "~", None), &clients).expect("should match a client");
+        assert_eq!(client_handle(matched.client()), Ok(0xb2));
+        assert!(matched.is_ambiguous());
+    }
+
+    #[test]
+    fn match_client_with_duplicate_title_takes_first() {
+        let clients = [client("0xa1", "kitty", "~"), client("0xb2", "kitty", "~")];
+        let matched = match_client(&toplevel("kitty", "~", None), &clients).expect("should match a client");
+        assert_eq!(client_handle(matched.client()), Ok(0xa1));
+    }
+
+    #[test]
+    fn match_client_without_match() {
+        let clients = [client("0xa1", "kitty", "~")];
+        assert!(match_client(&toplevel("kitty", "nvim", None), &clients).is_none());
+    }
+
+    #[test]
+    fn client_handle_parses_hex_address() {
+        assert_eq!(client_handle(&client("0x5713073a6a20", "kitty", "~")), Ok(0x5713073a6a20));
+        assert!(client_handle(&client("invalid", "kitty", "~")).is_err());
+    }
+
+    #[test]
+    fn display_title_falls_back_for_empty_titles() {
+        assert_eq!(display_title(&toplevel("kitty", "~", None), None), "~");
+        assert_eq!(display_title(&toplevel("kitty", "  ", None), None), "kitty");
+        assert_eq!(display_title(&toplevel("kitty", "", None), Some("(no title)")), "(no title)");
+        assert_eq!(display_title(&toplevel("", "", None), None), UNTITLED_LABEL);
+    }
+
+    #[test]
+    fn inactive_workspace_requires_monitors() {
+        assert_eq!(inactive_workspace(&client("0x1", "kitty", "~"), &[]), None);
+    }
+
+    #[test]
+    fn inactive_workspace_of_active_workspace() {
+        let monitors = [monitor(0, 1), monitor(1, 2)];
+        assert_eq!(inactive_workspace(&client_on_workspace(1, "1"), &monitors), None);
+        assert_eq!(inactive_workspace(&client_on_workspace(2, "2"), &monitors), None);
+    }
+
+    #[test]
+    fn inactive_workspace_of_hidden_workspace() {
+        let monitors = [monitor(0, 1), monitor(1, 2)];
+        assert_eq!(inactive_workspace(&client_on_workspace(3, "code"), &monitors), Some("code"));
+    }
+
+    #[test]
+    fn inactive_workspace_ignores_special_workspaces() {
+        let monitors = [monitor(0, 1), monitor(1, 2)];
+        assert_eq!(inactive_workspace(&client_on_workspace(-98, "special:scratchpad"), &monitors), None);
+    }
+}
@@ -1,10 +1,267 @@
-use gtk4::{Label, ScrolledWindow};
+use std::{
+    cell::{Cell, RefCell},
+    path::Path,
+    rc::Rc,
+    time::Duration,
+};
+
+use glib::{SourceId, clone, object::ObjectExt, variant::ToVariant};
+use gtk4::{
+    Align, ApplicationWindow, EventControllerMotion, IconLookupFlags, IconTheme, Label, Overlay, Picture, ProgressBar,
+    ScrolledWindow, TextDirection, Widget,
+    gdk_pixbuf::Pixbuf,
+    prelude::{ActionExt, ActionMapExt, CastNone, IsA, WidgetExt},
+};
+
+use crate::config::{Config, Page, RenderInterpolation};
+
+use paintable::NearestPaintable;
 
 pub mod outputs;
+mod paintable;
 pub mod region;
 pub mod windows;
 
+/// Filter for a built page which hides all entries not matching the query and returns whether any entry matches
+pub type PageFilter = Box<dyn Fn(&str) -> bool>;
+
 pub trait View {
     fn build(&self) -> ScrolledWindow;
     fn label(&self) -> Label;
+    /// get a filter for the page which was built by [`View::build`]
+    ///
+    /// pages without a filter are never matched by a search
+    fn filter(&self) -> Option<PageFilter> {
+        None
+    }
+}
+
+/// build the label of the notebook tab of a page
+pub fn tab_label(page: Page, config: &Config) -> Label {
+    let label = match page {
+        Page::Windows => "Windows",
+        Page::Outputs => "Outputs",
+        Page::Region => "Region",
+    };
+    Label::builder().css_classes([config.classes.tab_label.as_str()]).label(label).build()
+}
+
+/// check whether any of the searchable texts of an entry contains the query (case insensitive)
+pub fn matches_query(texts: &[&str], query: &str) -> bool {
+    let query = query.to_lowercase();
+    texts.iter().any(|text| text.to_lowercase().contains(&query))
+}
+
+/// icon which is shown on cards whose capture failed when no placeholder image is configured
+const PLACEHOLDER_ICON: &str = "image-missing";
+/// size of the placeholder icon in pixels
+const PLACEHOLDER_ICON_SIZE: i32 = 64;
+/// size of the application icons which are shown instead of previews in pixels
+///
+/// the icons are scaled to the size of the picture, therefore a bigger size is requested than for the placeholder
+const CLASS_ICON_SIZE: i32 = 256;
+
+/// colors of the badge text and its background which keep the badge legible on light and dark previews
+const BADGE_FOREGROUND: &str = "#ffffff";
+const BADGE_BACKGROUND: &str = "#000000b3";
+
+/// show the pixbuf on the picture using the configured interpolation
+///
+/// gtk always scales pictures smoothly, therefore nearest neighbor interpolation draws the pixbuf through a
+/// [`NearestPaintable`] which scales it whenever the drawn size changes
+pub fn show_pixbuf(picture: &Picture, pixbuf: Pixbuf, interpolation: RenderInterpolation) {
+    if interpolation == RenderInterpolation::Smooth {
+        return picture.set_pixbuf(Some(&pixbuf));
+    }
+    let paintable = NearestPaintable::new(pixbuf, picture.scale_factor());
+    picture.connect_scale_factor_notify(clone!(
+        #[weak]
+        paintable,
+        move |picture| paintable.set_scale_factor(picture.scale_factor())
+    ));
+    picture.set_paintable(Some(&paintable));
+}
+
+/// show the placeholder on a picture whose capture failed
+///
+/// falls back to the placeholder icon of the icon theme when the placeholder image can't be loaded
+pub fn set_placeholder(picture: &Picture, placeholder: Option<&Path>) {
+    if let Some(path) = placeholder {
+        match Pixbuf::from_file(path) {
+            Ok(pixbuf) => return picture.set_pixbuf(Some(&pixbuf)),
+            Err(err) => log::warn!("unable to load placeholder image {}: {err}", path.display()),
+        }
+    }
+    let icon = IconTheme::for_display(&picture.display()).lookup_icon(
+        PLACEHOLDER_ICON,
+        &[],
+        PLACEHOLDER_ICON_SIZE,
+        picture.scale_factor(),
+        TextDirection::None,
+        IconLookupFlags::empty(),
+    );
+    picture.set_paintable(Some(&icon));
+}
+
+/// show the icon of the application with the given class on a picture whose capture failed
+///
+/// the class is looked up as is and in lowercase in the icon theme. falls back to the placeholder when the icon theme
+/// doesn't contain an icon for the class
+pub fn set_class_icon(picture: &Picture, class: &str, placeholder: Option<&Path>) {
+    let theme = IconTheme::for_display(&picture.display());
+    let Some(name) =
+        [class.to_string(), class.to_lowercase()].into_iter().find(|name| !name.is_empty() && theme.has_icon(name))
+    else {
+        log::debug!("unable to find icon for class {class}: using placeholder instead");
+        return set_placeholder(picture, placeholder);
+    };
+    let icon = theme.lookup_icon(
+        &name,
+        &[],
+        CLASS_ICON_SIZE,
+        picture.scale_factor(),
+        TextDirection::None,
+        IconLookupFlags::empty(),
+    );
+    picture.set_paintable(Some(&icon));
+}
+
+/// overlay a badge onto the top left corner of the picture
+///
+/// the returned overlay replaces the picture in the card, the text of the badge is set using [`set_badge_text`]
+pub fn build_badge(picture: &Picture, class: &str) -> (Overlay, Label) {
+    let badge = Label::builder().halign(Align::Start).valign(Align::Start).can_target(false).css_classes([class]).build();
+    let overlay = Overlay::builder().child(picture).vexpand(true).build();
+    overlay.add_overlay(&badge);
+    (overlay, badge)
+}
+
+/// set the text of a badge which is drawn onto a translucent dark background
+pub fn set_badge_text(badge: &Label, text: &str) {
+    let text = glib::markup_escape_text(text);
+    badge.set_markup(&format!("<span foreground=\"{BADGE_FOREGROUND}\" background=\"{BADGE_BACKGROUND}\"> {text} </span>"));
+}
+
+/// select the token using the `win.select` action of the window
+///
+/// in multi select mode the action toggles the token in its state instead of exiting, therefore the card is marked with
+/// the class while its token is part of the selection
+pub fn select(widget: &impl IsA<Widget>, card: &impl IsA<Widget>, token: &str, class: &str) {
+    widget
+        .activate_action("win.select", Some(&token.to_variant()))
+        .expect("select action should be registered on the window");
+    let selected = widget
+        .root()
+        .and_downcast::<ApplicationWindow>()
+        .and_then(|window| window.lookup_action("select"))
+        .and_then(|action| action.state())
+        .and_then(|state| state.get::<Vec<String>>());
+    match selected {
+        Some(tokens) if tokens.iter().any(|selected| selected == token) => card.add_css_class(class),
+        _ => card.remove_css_class(class),
+    }
+}
+
+/// toggle the hover class on the card while the pointer is above its container
+pub fn connect_hover(container: &impl IsA<Widget>, card: &impl IsA<Widget>, class: &str) {
+    let motion = EventControllerMotion::new();
+    let (enter_card, enter_class) = (card.as_ref().downgrade(), class.to_string());
+    motion.connect_enter(move |_, _, _| {
+        if let Some(card) = enter_card.upgrade() {
+            card.add_css_class(&enter_class);
+        }
+    });
+    let (leave_card, leave_class) = (card.as_ref().downgrade(), class.to_string());
+    motion.connect_leave(move |_| {
+        if let Some(card) = leave_card.upgrade() {
+            card.remove_css_class(&leave_class);
+        }
+    });
+    container.add_controller(motion);
+}
+
+/// Css class which indicates that a click on a card was registered but more clicks are needed for the selection
+#[derive(Clone)]
+pub struct ArmedClass {
+    class: String,
+    timeout: Rc<RefCell<Option<SourceId>>>,
+}
+
+impl ArmedClass {
+    pub fn new(class: &str) -> Self {
+        Self { class: class.to_string(), timeout: Rc::new(RefCell::new(None)) }
+    }
+
+    /// apply the class to the widget until the double click time of gtk has passed
+    pub fn arm(&self, widget: &Widget) {
+        self.disarm(widget);
+        widget.add_css_class(&self.class);
+
+        let double_click_time = widget.settings().gtk_double_click_time().max(0) as u64;
+        let weak_widget = widget.downgrade();
+        let class = self.class.clone();
+        let timeout = self.timeout.clone();
+        let source = glib::timeout_add_local_once(Duration::from_millis(double_click_time), move || {
+            // the source is removed by glib after running once, therefore it must not be removed again
+            timeout.borrow_mut().take();
+            if let Some(widget) = weak_widget.upgrade() {
+                widget.remove_css_class(&class);
+            }
+        });
+        *self.timeout.borrow_mut() = Some(source);
+    }
+
+    /// remove the class from the widget
+    pub fn disarm(&self, widget: &Widget) {
+        if let Some(source) = self.timeout.borrow_mut().take() {
+            source.remove();
+        }
+        widget.remove_css_class(&self.class);
+    }
+}
+
+/// Progress of the captures of all pages which is shown above the notebook
+///
+/// failed captures count as finished as well, therefore the progress always completes
+#[derive(Clone)]
+pub struct CaptureProgress {
+    bar: ProgressBar,
+    enabled: Rc<Cell<bool>>,
+    total: Rc<Cell<u32>>,
+    finished: Rc<Cell<u32>>,
+}
+
+impl CaptureProgress {
+    pub fn new(enabled: bool, class: &str) -> Self {
+        let bar = ProgressBar::builder().visible(false).css_classes([class]).build();
+        Self { bar, enabled: Rc::new(Cell::new(enabled)), total: Rc::new(Cell::new(0)), finished: Rc::new(Cell::new(0)) }
+    }
+
+    pub fn widget(&self) -> &ProgressBar {
+        &self.bar
+    }
+
+    /// register a capture which is going to finish later
+    pub fn add(&self) {
+        self.total.set(self.total.get() + 1);
+        self.update();
+    }
+
+    /// mark a registered capture as finished regardless of whether it succeeded
+    pub fn finish(&self) {
+        self.finished.set((self.finished.get() + 1).min(self.total.get()));
+        self.update();
+    }
+
+    /// hide the progress bar for good even though not all captures finished
+    pub fn hide(&self) {
+        self.enabled.set(false);
+        self.bar.set_visible(false);
+    }
+
+    fn update(&self) {
+        let (finished, total) = (self.finished.get(), self.total.get());
+        self.bar.set_fraction(if total == 0 { 1.0 } else { finished as f64 / total as f64 });
+        self.bar.set_visible(self.enabled.get() && finished < total);
+    }
 }
@@ -1,21 +1,63 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+};
 
-use glib::{clone, variant::ToVariant};
+use glib::{
+    clone,
+    object::{Cast, ObjectExt},
+    variant::ToVariant,
+};
 use gtk4::{
     Box, Button, Fixed, GestureClick, Label, Picture, ScrolledWindow,
     prelude::{BoxExt, ButtonExt, EventControllerExt, FixedExt, WidgetExt, WidgetExtManual},
 };
 use hyprland::{
-    data::{Monitor, Monitors},
+    data::{CursorPosition, Monitor, Transforms},
     shared::HyprData,
 };
-use hyprland_preview_share_picker_lib::{image::Image, output::OutputManager};
-use tokio::sync::oneshot::{Receiver, Sender};
-use wayland_client::{Connection, protocol::wl_output::WlOutput};
+use hyprland_preview_share_picker_lib::{
+    error::Error,
+    image::Image,
+    output::{Output, OutputManager, OutputMode},
+};
+use tokio::{
+    sync::{
+        Semaphore,
+        oneshot::{Receiver, Sender},
+    },
+    task::JoinHandle,
+};
+use tracing::Instrument;
+use wayland_client::protocol::wl_output::{Transform, WlOutput};
+
+use crate::{
+    config::{self, Config, Page},
+    image::ImageExt,
+    reconnect::Reconnect,
+};
+
+use super::{
+    ArmedClass, CaptureProgress, PageFilter, View, build_badge, connect_hover, matches_query, select, set_badge_text,
+    set_placeholder, show_pixbuf, tab_label,
+};
 
-use crate::{config::Config, image::ImageExt, util::MonitorTransformExt};
+/// capture of an output which is spawned once the outputs page is shown
+pub type DeferredCapture = Pin<std::boxed::Box<dyn Future<Output = ()> + Send>>;
 
-use super::View;
+impl From<config::ContentFit> for gtk4::ContentFit {
+    fn from(value: config::ContentFit) -> Self {
+        match value {
+            config::ContentFit::Fill => gtk4::ContentFit::Fill,
+            config::ContentFit::Contain => gtk4::ContentFit::Contain,
+            config::ContentFit::Cover => gtk4::ContentFit::Cover,
+            config::ContentFit::ScaleDown => gtk4::ContentFit::ScaleDown,
+        }
+    }
+}
 
 struct MonitorArea {
     min_x: i32,
@@ -29,8 +71,8 @@ struct MonitorArea {
     offset_y: i32,
 }
 
-impl From<&Vec<Monitor>> for MonitorArea {
-    fn from(monitors: &Vec<Monitor>) -> Self {
+impl From<&Vec<MonitorLayout>> for MonitorArea {
+    fn from(monitors: &Vec<MonitorLayout>) -> Self {
         let min_x = monitors.iter().min_by_key(|m| m.x).map(|m| m.x).unwrap_or_default();
         let min_y = monitors.iter().min_by_key(|m| m.y).map(|m| m.y).unwrap_or_default();
         let max_x = monitors.iter().max_by_key(|m| m.x + m.width as i32).map(|m| m.x + m.width as i32).unwrap_or_default();
@@ -44,34 +86,246 @@ impl From<&Vec<Monitor>> for MonitorArea {
     }
 }
 
+/// Position and size of a single monitor in the layout
+///
+/// this is usually built from a hyprland monitor but can also be built from the wayland output alone
+#[derive(Debug, Clone)]
+struct MonitorLayout {
+    id: usize,
+    name: String,
+    description: String,
+    make: String,
+    model: String,
+    x: i32,
+    y: i32,
+    width: u16,
+    height: u16,
+    scale: f32,
+    transform: Transforms,
+}
+
+impl MonitorLayout {
+    fn from_monitor(id: usize, monitor: &Monitor) -> Self {
+        Self {
+            id,
+            name: monitor.name.clone(),
+            description: monitor.description.clone(),
+            make: monitor.make.clone(),
+            model: monitor.model.clone(),
+            x: monitor.x,
+            y: monitor.y,
+            width: monitor.width,
+            height: monitor.height,
+            scale: monitor.scale,
+            transform: monitor.transform,
+        }
+    }
+
+    fn from_output(id: usize, output: &Output) -> Option<Self> {
+        let (Some(name), Some(geometry), Some(mode)) = (&output.name, &output.geometry, &output.mode) else {
+            return None;
+        };
+        let transform = match geometry.transform {
+            Transform::_90 => Transforms::Normal90,
+            Transform::_180 => Transforms::Normal180,
+            Transform::_270 => Transforms::Normal270,
+            Transform::Flipped => Transforms::Flipped,
+            Transform::Flipped90 => Transforms::Flipped90,
+            Transform::Flipped180 => Transforms::Flipped180,
+            Transform::Flipped270 => Transforms::Flipped270,
+            _ => Transforms::Normal,
+        };
+        Some(Self {
+            id,
+            name: name.clone(),
+            description: output.description.clone().unwrap_or_default(),
+            make: geometry.make.clone(),
+            model: geometry.model.clone(),
+            x: geometry.x,
+            y: geometry.y,
+            width: mode.width as u16,
+            height: mode.height as u16,
+            scale: output.scale.unwrap_or(1.0) as f32,
+            transform,
+        })
+    }
+
+    /// swap the width and height for rotated monitors
+    fn apply_transform(&mut self) {
+        match self.transform {
+            Transforms::Normal | Transforms::Normal180 | Transforms::Flipped | Transforms::Flipped180 => {}
+            Transforms::Normal90 | Transforms::Normal270 | Transforms::Flipped90 | Transforms::Flipped270 => {
+                std::mem::swap(&mut self.height, &mut self.width);
+            }
+        }
+    }
+}
+
 pub struct OutputsView<'a> {
     config: &'a Config,
     manager: Arc<OutputManager>,
-    monitors: Vec<Monitor>,
+    reconnect: Arc<Reconnect<OutputManager>>,
+    monitors: Vec<MonitorLayout>,
     area: MonitorArea,
+    cursor_monitor: Option<String>,
+    progress: CaptureProgress,
+    /// built cards with the name and description of their monitor
+    cards: RefCell<Vec<(Button, String, String)>>,
+    /// tasks capturing the outputs of the built cards
+    capture_tasks: RefCell<Vec<JoinHandle<()>>>,
+    /// captures of the outputs which are spawned once the page is shown
+    deferred_captures: RefCell<Vec<DeferredCapture>>,
 }
 
 impl<'a> OutputsView<'a> {
-    pub fn new(connection: &'a Connection, config: &'a Config) -> Result<Self, String> {
-        let manager = OutputManager::new(connection)
-            .map(Arc::new)
-            .map_err(|err| format!("unable to create new output manager from connection: {err}"))?;
-        let mut monitors = Monitors::get()
-            .map(|monitors| monitors.into_iter().filter(|monitor| !monitor.disabled).collect::<Vec<_>>())
-            .map_err(|err| format!("unable to get monitors from hyprland socket: {err}"))?;
+    /// create a new outputs view
+    ///
+    /// when no hyprland monitors are provided (e.g. because the hyprland socket is unavailable) the layout is built
+    /// from the wayland output geometry instead
+    pub fn new(
+        manager: Arc<OutputManager>,
+        reconnect: Arc<Reconnect<OutputManager>>,
+        monitors: &[Monitor],
+        progress: CaptureProgress,
+        config: &'a Config,
+    ) -> Self {
+        let hyprland_available = !monitors.is_empty();
+        let mut monitors = if !hyprland_available {
+            log::warn!("received no hyprland monitors: falling back to wayland output geometry");
+            manager
+                .outputs
+                .iter()
+                .enumerate()
+                .filter_map(|(id, (_, output))| MonitorLayout::from_output(id, output))
+                .collect::<Vec<_>>()
+        } else {
+            monitors
+                .iter()
+                .filter(|monitor| !monitor.disabled)
+                .enumerate()
+                .map(|(id, monitor)| MonitorLayout::from_monitor(id, monitor))
+                .collect::<Vec<_>>()
+        };
 
         // apply the transformations (rotations) to all monitors
         monitors.iter_mut().for_each(|m| m.apply_transform());
+        // the cursor position is only provided by the hyprland socket
+        let cursor_monitor = if hyprland_available { Self::find_cursor_monitor(&monitors) } else { None };
         let area = MonitorArea::from(&monitors);
-        let mut view = Self { config, manager, monitors, area };
+        let mut view = Self {
+            config,
+            manager,
+            reconnect,
+            monitors,
+            area,
+            cursor_monitor,
+            progress,
+            cards: RefCell::new(Vec::new()),
+            capture_tasks: RefCell::new(Vec::new()),
+            deferred_captures: RefCell::new(Vec::new()),
+        };
         if config.outputs.respect_output_scaling {
             view.apply_output_scaling();
             view.area = MonitorArea::from(&view.monitors)
         }
-        Ok(view)
+        Self::sort_spatially(&mut view.monitors);
+        view
+    }
+
+    /// sort the monitors left-to-right and top-to-bottom by their position
+    ///
+    /// monitors which vertically overlap the monitors above them form a row, such that side by side monitors with
+    /// different vertical offsets stay in the same row whereas stacked monitors are ordered from top to bottom
+    fn sort_spatially(monitors: &mut [MonitorLayout]) {
+        monitors.sort_by_key(|m| (m.y, m.x));
+        let mut row = 0;
+        let mut row_bottom = None;
+        let rows = monitors
+            .iter()
+            .map(|m| {
+                let bottom = m.y + m.height as i32;
+                match row_bottom {
+                    Some(current) if m.y < current => row_bottom = Some(bottom.max(current)),
+                    Some(_) => {
+                        row += 1;
+                        row_bottom = Some(bottom);
+                    }
+                    None => row_bottom = Some(bottom),
+                }
+                (m.id, row)
+            })
+            .collect::<HashMap<_, _>>();
+        monitors.sort_by_key(|m| (rows.get(&m.id).copied().unwrap_or_default(), m.x));
+    }
+
+    /// take the tasks capturing the outputs of the built page
+    pub fn take_capture_tasks(&self) -> Vec<JoinHandle<()>> {
+        self.capture_tasks.take()
+    }
+
+    /// take the captures which are expected to be spawned once the page is shown
+    ///
+    /// the captures are only deferred when `outputs.defer_captures` is enabled
+    pub fn take_deferred_captures(&self) -> Vec<DeferredCapture> {
+        self.deferred_captures.take()
+    }
+
+    /// get the name of the monitor the cursor is currently on
+    ///
+    /// the monitors are expected to have their transformation applied but not their scaling
+    fn find_cursor_monitor(monitors: &[MonitorLayout]) -> Option<String> {
+        let cursor = match CursorPosition::get() {
+            Ok(cursor) => cursor,
+            Err(err) => {
+                log::warn!("unable to get cursor position from hyprland socket: {err}");
+                return None;
+            }
+        };
+        monitors
+            .iter()
+            .find(|m| {
+                let width = (m.width as f32 / m.scale) as i64;
+                let height = (m.height as f32 / m.scale) as i64;
+                let (x, y) = (m.x as i64, m.y as i64);
+                cursor.x >= x && cursor.x < x + width && cursor.y >= y && cursor.y < y + height
+            })
+            .map(|m| m.name.clone())
+    }
+
+    /// find the monitor which belongs to a wayland output
+    ///
+    /// when multiple monitors share the same name they're disambiguated using the description, make and model of the output
+    ///
+    /// returns the index of the monitor which isn't contained in the `used` indices
+    fn find_monitor(&self, name: &str, output: &Output, used: &[usize]) -> Option<usize> {
+        let candidates = self
+            .monitors
+            .iter()
+            .enumerate()
+            .filter(|(index, m)| m.name.eq(name) && !used.contains(index))
+            .collect::<Vec<_>>();
+        if candidates.len() <= 1 {
+            return candidates.first().map(|(index, _)| *index);
+        }
+
+        log::warn!("found {} hyprland monitors with name {name}: disambiguating by description", candidates.len());
+        candidates
+            .iter()
+            .find(|(_, m)| output.description.as_ref().is_some_and(|description| m.description.eq(description)))
+            .or_else(|| {
+                candidates.iter().find(|(_, m)| {
+                    output.geometry.as_ref().is_some_and(|geometry| m.make.eq(&geometry.make) && m.model.eq(&geometry.model))
+                })
+            })
+            .or(candidates.first())
+            .map(|(index, _)| *index)
     }
 
     fn apply_output_scaling(&mut self) {
+        if let Some(scale) = self.config.outputs.scale_override {
+            log::warn!("overriding the scale of all monitors in the layout with {scale}");
+            self.monitors.iter_mut().for_each(|m| m.scale = scale);
+        }
         // very ugly code to do some very ugly things
         let mut translations = HashMap::new();
         self.monitors.iter().for_each(|m| {
@@ -127,62 +381,193 @@ impl<'a> OutputsView<'a> {
 impl View for OutputsView<'_> {
     fn build(&self) -> ScrolledWindow {
         let container = Fixed::builder().hexpand(false).vexpand(false).build();
-        let scrolled_window =
-            ScrolledWindow::builder().child(&container).css_classes([self.config.classes.notebook_page.as_str()]).build();
-
-        self.manager.outputs.iter().for_each(|(wl_output, output)| {
-            let name = match &output.name {
-                Some(name) => name,
-                None => return log::error!("output {output:?} does not have a name"),
-            };
-            let Some(monitor) = self.monitors.iter().find(|m| m.name.eq(name)).cloned() else {
-                return log::error!("output {name} does not exist on hyprland");
-            };
-            let output_card = OutputCard::new(&monitor, self.config, wl_output, &self.area, self.manager.clone());
-            let card = match output_card.build() {
-                Ok(card) => card,
-                Err(err) => return log::error!("unable to build output card for output {name}: {err}"),
+        let scrolled_window = ScrolledWindow::builder().css_classes([self.config.classes.notebook_page.as_str()]).build();
+
+        let captures = Arc::new(Semaphore::new(self.config.outputs.max_concurrent_captures.max(1)));
+
+        let mut used_monitors = Vec::new();
+        let mut outputs = self
+            .manager
+            .outputs
+            .iter()
+            .filter_map(|(wl_output, output)| {
+                let Some(name) = &output.name else {
+                    log::error!("output {output:?} does not have a name");
+                    return None;
+                };
+                let Some(index) = self.find_monitor(name, output, &used_monitors) else {
+                    log::error!("output {name} does not have a monitor layout");
+                    return None;
+                };
+                used_monitors.push(index);
+                Some((index, wl_output))
+            })
+            .collect::<Vec<_>>();
+        // the cards are built in the spatial order of the monitors such that the keyboard navigation follows the layout
+        outputs.sort_by_key(|(index, _)| *index);
+
+        let mut requests = Vec::new();
+        let mut layouts = Vec::new();
+        outputs.into_iter().for_each(|(index, wl_output)| {
+            let monitor = &self.monitors[index];
+            let output_card = OutputCard::new(
+                monitor,
+                self.config,
+                wl_output,
+                &self.area,
+                self.manager.clone(),
+                self.reconnect.clone(),
+                captures.clone(),
+            );
+            let (card, tx) = match output_card.build(&self.progress) {
+                Ok(built) => built,
+                Err(err) => return log::error!("unable to build output card for output {}: {err}", monitor.name),
             };
-            output_card.append_on_allocation(&container, &card);
+            layouts.push(output_card.layout(&card));
+            self.cards.borrow_mut().push((card, monitor.name.clone(), monitor.description.clone()));
+            requests.push((output_card, tx));
         });
 
+        // capture the output the cursor is on first since it's the most likely selection
+        requests.sort_by_key(|(output_card, _)| self.cursor_monitor.as_ref() != Some(&output_card.monitor.name));
+        requests.into_iter().for_each(|(output_card, tx)| {
+            let task = output_card.capture_task(tx);
+            if self.config.outputs.defer_captures && self.cursor_monitor.as_ref() != Some(&output_card.monitor.name) {
+                self.deferred_captures.borrow_mut().push(std::boxed::Box::pin(task));
+            } else {
+                self.capture_tasks.borrow_mut().push(tokio::spawn(task));
+            }
+        });
+
+        if let Some(token) = &self.config.outputs.all_screens_token {
+            let page = Box::builder().orientation(gtk4::Orientation::Vertical).build();
+            let button = Button::builder()
+                .label("Select all outputs")
+                .halign(gtk4::Align::Center)
+                .css_classes([self.config.classes.all_screens_button.as_str()])
+                .build();
+            button.connect_clicked(clone!(
+                #[strong]
+                token,
+                move |btn| {
+                    btn.activate_action("win.select", Some(&token.to_variant()))
+                        .expect("select action should be registered on the window")
+                }
+            ));
+            container.set_vexpand(true);
+            page.append(&container);
+            page.append(&button);
+            scrolled_window.set_child(Some(&page));
+        } else {
+            scrolled_window.set_child(Some(&container));
+        }
+        connect_layout(&scrolled_window, &container, layouts);
+
         scrolled_window
     }
 
     fn label(&self) -> Label {
-        Label::builder().css_classes([self.config.classes.tab_label.as_str()]).label("Outputs").build()
+        tab_label(Page::Outputs, self.config)
+    }
+
+    fn filter(&self) -> Option<PageFilter> {
+        let cards = self.cards.borrow().clone();
+        Some(std::boxed::Box::new(move |query| {
+            cards.iter().fold(false, |matched, (card, name, description)| {
+                let visible = matches_query(&[name, description], query);
+                card.set_visible(visible);
+                matched || visible
+            })
+        }))
     }
 }
 
+/// Function positioning a single card according to the current allocation of the container
+type CardLayout = std::boxed::Box<dyn Fn(&Fixed)>;
+
+/// Position the cards once the container is allocated and again whenever its size changes
+///
+/// the viewport of the scrolled window updates the page size of its adjustments when it's resized, the container is
+/// only allocated afterwards. therefore the cards are positioned on the next frame instead of in the notify handler
+fn connect_layout(scrolled_window: &ScrolledWindow, container: &Fixed, layouts: Vec<CardLayout>) {
+    let last_allocation = Cell::new((0, 0));
+    // returns whether the container has an allocation
+    let relayout = Rc::new(move |container: &Fixed| {
+        let size = (container.width(), container.height());
+        if size.0 == 0 || size.1 == 0 {
+            return false;
+        }
+        if last_allocation.replace(size) != size {
+            layouts.iter().for_each(|layout| layout(container));
+        }
+        true
+    });
+
+    // listen to ticks only until the container has an allocation
+    container.add_tick_callback(clone!(
+        #[strong]
+        relayout,
+        move |container, _| if relayout(container) { glib::ControlFlow::Break } else { glib::ControlFlow::Continue }
+    ));
+
+    [scrolled_window.hadjustment(), scrolled_window.vadjustment()].iter().for_each(|adjustment| {
+        adjustment.connect_notify_local(
+            Some("page-size"),
+            clone!(
+                #[weak]
+                container,
+                #[strong]
+                relayout,
+                move |_, _| {
+                    container.add_tick_callback(clone!(
+                        #[strong]
+                        relayout,
+                        move |container, _| {
+                            relayout(container);
+                            glib::ControlFlow::Break
+                        }
+                    ));
+                }
+            ),
+        );
+    });
+}
+
 struct OutputCard<'a> {
-    monitor: &'a Monitor,
+    monitor: &'a MonitorLayout,
     config: &'a Config,
     manager: Arc<OutputManager>,
+    reconnect: Arc<Reconnect<OutputManager>>,
     output: &'a WlOutput,
     area: &'a MonitorArea,
+    captures: Arc<Semaphore>,
 }
 
 impl<'a> OutputCard<'a> {
     fn new(
-        monitor: &'a Monitor,
+        monitor: &'a MonitorLayout,
         config: &'a Config,
         output: &'a WlOutput,
         area: &'a MonitorArea,
         manager: Arc<OutputManager>,
+        reconnect: Arc<Reconnect<OutputManager>>,
+        captures: Arc<Semaphore>,
     ) -> Self {
-        Self { monitor, config, output, manager, area }
+        Self { monitor, config, output, manager, reconnect, area, captures }
     }
 
-    pub fn build(&self) -> Result<Button, String> {
+    /// build the card without capturing the output
+    ///
+    /// the returned sender is expected to be passed to [`OutputCard::capture_task`]
+    pub fn build(&self, progress: &CaptureProgress) -> Result<(Button, Sender<Result<Image, Error>>), String> {
         let (tx, rx) = tokio::sync::oneshot::channel();
         let picture = self.build_picture();
         let card = self.build_card(&picture);
         let container = self.build_card_container(&card);
 
-        self.request_frame(tx);
-        self.update_frame_lazily(card.clone(), picture.clone(), rx);
+        self.update_frame_lazily(card.clone(), picture.clone(), rx, progress.clone());
 
-        Ok(container)
+        Ok((container, tx))
     }
 
     fn build_picture(&self) -> Picture {
@@ -190,7 +575,7 @@ impl<'a> OutputCard<'a> {
             .vexpand(true)
             .valign(gtk4::Align::Fill)
             .halign(gtk4::Align::Fill)
-            .content_fit(gtk4::ContentFit::Fill)
+            .content_fit(self.config.outputs.content_fit.into())
             .css_classes([self.config.classes.image.as_str()])
             .build()
     }
@@ -217,12 +602,22 @@ impl<'a> OutputCard<'a> {
         if self.area.max_y != self.monitor.y + self.monitor.height as i32 {
             container.set_margin_bottom(self.config.outputs.spacing as i32);
         }
-        container.append(picture);
+        if self.config.image.badge {
+            let (overlay, badge) = build_badge(picture, &self.config.classes.image_badge);
+            set_badge_text(&badge, &self.monitor.name);
+            container.append(&overlay);
+        } else {
+            container.append(picture);
+        }
 
         if self.config.outputs.show_label {
+            let text = match self.mode() {
+                Some(mode) if self.config.outputs.show_mode => format!("{} ({mode})", self.monitor.name),
+                _ => self.monitor.name.clone(),
+            };
             let label = Label::builder()
                 .max_width_chars(1)
-                .label(&self.monitor.name)
+                .label(&text)
                 .ellipsize(gtk4::pango::EllipsizeMode::End)
                 .single_line_mode(true)
                 .css_classes([self.config.classes.image_label.as_str()])
@@ -235,133 +630,193 @@ impl<'a> OutputCard<'a> {
         container
     }
 
+    /// get the current mode of the output of the card
+    fn mode(&self) -> Option<&OutputMode> {
+        self.manager
+            .outputs
+            .iter()
+            .find(|(wl_output, _)| wl_output == self.output)
+            .and_then(|(_, output)| output.mode.as_ref())
+    }
+
     fn build_card_container(&self, card: &Box) -> Button {
-        let container = Button::builder().focusable(true).child(card).build();
+        let tooltip = match self.mode() {
+            Some(mode) => format!("{}\n{mode}", self.monitor.name),
+            None => self.monitor.name.clone(),
+        };
+        let container = Button::builder().focusable(true).tooltip_text(tooltip).child(card).build();
 
         let gesture = GestureClick::new();
         gesture.set_propagation_phase(gtk4::PropagationPhase::Capture);
-        let clicks = self.config.windows.clicks;
-        let name = &self.monitor.name;
+        let clicks = self.config.outputs.clicks;
+        let token = self.config.outputs.screen_token.replace("{name}", &self.monitor.name);
+        let armed = ArmedClass::new(&self.config.classes.image_card_armed);
+        let hover_class = self.config.classes.image_card_hover.clone();
+        let selected_class = self.config.classes.image_card_selected.clone();
         gesture.connect_released(clone!(
             #[strong]
-            name,
+            token,
+            #[strong]
+            selected_class,
+            #[weak]
+            card,
             move |gesture, n, _, _| {
-                if n as i64 == clicks as i64
+                if (n as i64) < clicks as i64 {
+                    armed.arm(card.upcast_ref());
+                } else if n as i64 == clicks as i64
                     && let Some(widget) = gesture.widget()
                 {
-                    widget
-                        .activate_action("win.select", Some(&format!("screen:{name}").to_variant()))
-                        .expect("select action should be registered on the window")
+                    armed.disarm(card.upcast_ref());
+                    card.remove_css_class(&hover_class);
+                    select(&widget, &card, &token, &selected_class);
                 }
             }
         ));
         container.add_controller(gesture);
+        connect_hover(&container, card, &self.config.classes.image_card_hover);
         container.connect_activate(clone!(
-            #[strong]
-            name,
-            move |child| {
-                child
-                    .activate_action("win.select", Some(&format!("screen:{name}").to_variant()))
-                    .expect("select action should be registered on the window")
-            }
+            #[weak]
+            card,
+            move |child| select(child, &card, &token, &selected_class)
         ));
         container
     }
 
-    pub fn append_on_allocation(&self, container: &Fixed, card: &Button) {
+    /// get the function positioning the card according to the current allocation of the container
+    ///
+    /// the card is put into the container the first time the function is called
+    pub fn layout(&self, card: &Button) -> CardLayout {
         let &MonitorArea { aspect_ratio, width: monitors_width, height: monitors_height, offset_x, offset_y, .. } =
             self.area;
-        let &Monitor { height, width, x, y, .. } = self.monitor;
-
-        container.add_tick_callback(clone!(
-            #[strong]
-            card,
-            move |container, _| {
-                let allocation = container.allocation();
-                // listen to ticks until we have an allocation
-                if allocation.width() == 0 || allocation.height() == 0 {
-                    glib::ControlFlow::Continue
+        let &MonitorLayout { height, width, x, y, .. } = self.monitor;
+        let card = card.clone();
+
+        std::boxed::Box::new(move |container: &Fixed| {
+            let allocation = container.allocation();
+            let container_aspect_ratio = allocation.width() as f64 / allocation.height() as f64;
+            let monitors_width_f = monitors_width as f64;
+            let monitors_height_f = monitors_height as f64;
+            let transform_x = |x: i32| {
+                if aspect_ratio > container_aspect_ratio {
+                    (x as f64 / monitors_width_f) * allocation.width() as f64
                 } else {
-                    let container_aspect_ratio = allocation.width() as f64 / allocation.height() as f64;
-                    let monitors_width_f = monitors_width as f64;
-                    let monitors_height_f = monitors_height as f64;
-                    let transform_x = |x: i32| {
-                        if aspect_ratio > container_aspect_ratio {
-                            (x as f64 / monitors_width_f) * allocation.width() as f64
-                        } else {
-                            (x as f64 / monitors_width_f) * allocation.height() as f64 * aspect_ratio
-                        }
-                    };
-                    let transform_y = |y: i32| {
-                        if aspect_ratio > container_aspect_ratio {
-                            (y as f64 / monitors_height_f) * allocation.width() as f64 / aspect_ratio
-                        } else {
-                            (y as f64 / monitors_height_f) * allocation.height() as f64
-                        }
-                    };
+                    (x as f64 / monitors_width_f) * allocation.height() as f64 * aspect_ratio
+                }
+            };
+            let transform_y = |y: i32| {
+                if aspect_ratio > container_aspect_ratio {
+                    (y as f64 / monitors_height_f) * allocation.width() as f64 / aspect_ratio
+                } else {
+                    (y as f64 / monitors_height_f) * allocation.height() as f64
+                }
+            };
 
-                    card.set_width_request(transform_x(width as i32) as i32);
-                    card.set_height_request(transform_y(height as i32) as i32);
+            card.set_width_request(transform_x(width as i32) as i32);
+            card.set_height_request(transform_y(height as i32) as i32);
 
-                    let transformed_monitor_width = transform_x(monitors_width);
-                    let transformed_monitor_height = transform_x(monitors_height);
+            let transformed_monitor_width = transform_x(monitors_width);
+            let transformed_monitor_height = transform_x(monitors_height);
 
-                    let px_offset_x = (allocation.width() as f64 - transformed_monitor_width).max(0.0) / 2.0;
-                    let px_offset_y = (allocation.height() as f64 - transformed_monitor_height).max(0.0) / 2.0;
+            let px_offset_x = (allocation.width() as f64 - transformed_monitor_width).max(0.0) / 2.0;
+            let px_offset_y = (allocation.height() as f64 - transformed_monitor_height).max(0.0) / 2.0;
 
-                    container.put(&card, px_offset_x + transform_x(offset_x + x), px_offset_y + transform_y(offset_y + y));
-                    glib::ControlFlow::Break
-                }
+            let card_x = px_offset_x + transform_x(offset_x + x);
+            let card_y = px_offset_y + transform_y(offset_y + y);
+            if card.parent().is_some() {
+                container.move_(&card, card_x, card_y);
+            } else {
+                container.put(&card, card_x, card_y);
             }
-        ));
+        })
     }
 
-    fn request_frame(&self, tx: Sender<Image>) {
-        let resize_size = self.config.image.resize_size;
-        let manager = self.manager.clone();
+    /// get the task capturing the output, the capture only starts once the task is spawned
+    fn capture_task(&self, tx: Sender<Result<Image, Error>>) -> impl Future<Output = ()> + Send + 'static {
+        let resize_size = self.config.outputs.resize_size.unwrap_or(self.config.image.resize_size);
         let name = &self.monitor.name;
-        let output = self.output;
         let transform = self.monitor.transform;
+        let flip = self.config.image.flip;
+        let captures = self.captures.clone();
+        let capture = self.manager.capture_output_image(self.output);
+        let reconnect = self.reconnect.clone();
+        let span = tracing::debug_span!("capture", output = %name);
 
-        tokio::spawn(clone!(
+        let task = clone!(
             #[strong]
             name,
-            #[strong]
-            output,
-            #[to_owned]
-            manager,
             async move {
-                let buffer = match manager.to_owned().capture_output(&output) {
-                    Ok(buffer) => buffer,
-                    Err(err) => return log::error!("unable to capture output {name}: {err}"),
+                let _permit = match captures.acquire().await {
+                    Ok(permit) => permit,
+                    Err(err) => return log::error!("unable to acquire capture permit for output {name}: {err}"),
+                };
+                let result = match capture.await {
+                    // the wayland output belongs to the lost connection, therefore it's looked up again by its name
+                    Err(err) if err.is_connection_lost() => match reconnect.manager() {
+                        Ok(manager) => {
+                            let capture = manager.find_output(&name).map(|(output, _)| manager.capture_output_image(output));
+                            match capture {
+                                Some(capture) => capture.await,
+                                None => Err(Error::OutputNotFound(name.clone())),
+                            }
+                        }
+                        Err(reconnect_err) => {
+                            log::error!("{reconnect_err}");
+                            Err(err)
+                        }
+                    },
+                    result => result,
                 };
-                let mut img = match Image::new(buffer) {
+                let mut img = match result {
                     Ok(img) => match img.into_rgb() {
                         Ok(img) => img,
                         Err(err) => return log::error!("unable to convert Xrgb image to rgb: {err}"),
                     },
-                    Err(err) => return log::error!("unable to create image from buffer: {err}"),
+                    Err(err) => {
+                        log::error!("unable to capture output {name}: {err}");
+                        let _ = tx.send(Err(err));
+                        return;
+                    }
                 };
 
-                img.resize_to_fit(resize_size);
+                // the image is transformed before it's resized such that the resize filter smooths the final orientation
                 img = img.transform(transform.into());
+                img.resize_to_fit(resize_size);
+                img.apply_flip(flip);
 
-                if tx.send(img).is_err() {
+                if tx.send(Ok(img)).is_err() {
                     log::error!("unable to transmit image for name {name}: channel is closed");
                 };
                 log::debug!("transmitted image for output {name}");
             }
-        ));
+        );
+        task.instrument(span)
     }
 
-    fn update_frame_lazily(&self, card: Box, picture: Picture, rx: Receiver<Image>) {
+    fn update_frame_lazily(
+        &self,
+        card: Box,
+        picture: Picture,
+        rx: Receiver<Result<Image, Error>>,
+        progress: CaptureProgress,
+    ) {
         let loading_class = self.config.classes.image_card_loading.clone();
+        let render_interpolation = self.config.image.render_interpolation;
+        let placeholder = self.config.image.placeholder_path.as_ref().map(|path| self.config.resolve_path(path));
         let name = self.monitor.name.clone();
-        glib::spawn_future_local(async move {
+        let span = tracing::debug_span!("update", output = %name);
+        let task = async move {
             let img = match rx.await {
-                Ok(img) => img,
+                Ok(Ok(img)) => img,
+                Ok(Err(err)) => {
+                    // the error identifies the failed capture without having to look into the logs
+                    set_placeholder(&picture, placeholder.as_deref());
+                    picture.set_tooltip_text(Some(&err.to_string()));
+                    card.remove_css_class(&loading_class);
+                    return;
+                }
                 Err(err) => {
                     log::error!("unable to receive image for output {name}: {err}");
+                    set_placeholder(&picture, placeholder.as_deref());
                     card.remove_css_class(&loading_class);
                     return;
                 }
@@ -369,11 +824,24 @@ impl<'a> OutputCard<'a> {
 
             let pixbuf = match img.into_pixbuf() {
                 Ok(pixbuf) => pixbuf,
-                Err(err) => return log::error!("unable to create pixbuf for output {name} image: {err}"),
+                Err(err) => {
+                    log::error!("unable to create pixbuf for output {name} image: {err}");
+                    set_placeholder(&picture, placeholder.as_deref());
+                    card.remove_css_class(&loading_class);
+                    return;
+                }
             };
 
-            picture.set_pixbuf(Some(&pixbuf));
+            show_pixbuf(&picture, pixbuf, render_interpolation);
             card.remove_css_class(&loading_class);
-        });
+        };
+        progress.add();
+        glib::spawn_future_local(
+            async move {
+                task.await;
+                progress.finish();
+            }
+            .instrument(span),
+        );
     }
 }
@@ -0,0 +1,83 @@
+use gtk4::{gdk, gdk_pixbuf::Pixbuf, glib, subclass::prelude::ObjectSubclassIsExt};
+
+mod imp {
+    use std::cell::{Cell, RefCell};
+
+    use gtk4::{
+        gdk::{self, Texture, prelude::PaintableExt, subclass::prelude::PaintableImpl},
+        gdk_pixbuf::{InterpType, Pixbuf},
+        glib,
+        subclass::prelude::{ObjectImpl, ObjectSubclass},
+    };
+
+    #[derive(Default)]
+    pub struct NearestPaintable {
+        pub pixbuf: RefCell<Option<Pixbuf>>,
+        /// device pixels per logical pixel of the widget the paintable is drawn on
+        pub scale_factor: Cell<i32>,
+        /// texture of the pixbuf scaled to the last drawn size in device pixels
+        pub scaled: RefCell<Option<((i32, i32), Texture)>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for NearestPaintable {
+        const NAME: &'static str = "HyprlandPreviewSharePickerNearestPaintable";
+        type Type = super::NearestPaintable;
+        type Interfaces = (gdk::Paintable,);
+    }
+
+    impl ObjectImpl for NearestPaintable {}
+
+    impl PaintableImpl for NearestPaintable {
+        fn intrinsic_width(&self) -> i32 {
+            self.pixbuf.borrow().as_ref().map(|pixbuf| pixbuf.width()).unwrap_or(0)
+        }
+
+        fn intrinsic_height(&self) -> i32 {
+            self.pixbuf.borrow().as_ref().map(|pixbuf| pixbuf.height()).unwrap_or(0)
+        }
+
+        fn snapshot(&self, snapshot: &gdk::Snapshot, width: f64, height: f64) {
+            let Some(pixbuf) = self.pixbuf.borrow().clone() else {
+                return;
+            };
+            let scale_factor = self.scale_factor.get().max(1) as f64;
+            let size = (((width * scale_factor).round() as i32).max(1), ((height * scale_factor).round() as i32).max(1));
+            let mut scaled = self.scaled.borrow_mut();
+            // the pixbuf is only scaled again when the drawn size changed
+            if scaled.as_ref().is_none_or(|(scaled_size, _)| *scaled_size != size) {
+                *scaled = pixbuf
+                    .scale_simple(size.0, size.1, InterpType::Nearest)
+                    .map(|scaled| (size, Texture::for_pixbuf(&scaled)));
+            }
+            if let Some((_, texture)) = scaled.as_ref() {
+                texture.snapshot(snapshot, width, height);
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Paintable drawing a pixbuf scaled with nearest neighbor interpolation
+    ///
+    /// gtk always scales textures smoothly, therefore the pixbuf is scaled to the drawn size in device pixels
+    /// beforehand. the intrinsic size stays the size of the pixbuf such that the paintable doesn't affect the layout
+    pub struct NearestPaintable(ObjectSubclass<imp::NearestPaintable>) @implements gdk::Paintable;
+}
+
+impl NearestPaintable {
+    pub fn new(pixbuf: Pixbuf, scale_factor: i32) -> Self {
+        let paintable: Self = glib::Object::new();
+        paintable.imp().pixbuf.replace(Some(pixbuf));
+        paintable.imp().scale_factor.set(scale_factor);
+        paintable
+    }
+
+    /// update the scale factor when the widget moved to a monitor with a different scale
+    pub fn set_scale_factor(&self, scale_factor: i32) {
+        if self.imp().scale_factor.replace(scale_factor) != scale_factor {
+            self.imp().scaled.replace(None);
+            gdk::prelude::PaintableExt::invalidate_contents(self);
+        }
+    }
+}
@@ -7,9 +7,9 @@ use gtk4::{
 };
 use regex::Regex;
 
-use crate::config::Config;
+use crate::config::{Config, Page};
 
-use super::View;
+use super::{View, tab_label};
 
 pub struct RegionView<'a> {
     config: &'a Config,
@@ -21,10 +21,38 @@ impl<'a> RegionView<'a> {
     pub fn new(config: &'a Config) -> Result<Self, String> {
         let args = shlex::split(&config.region.command)
             .ok_or(format!("received invalid region command: {}", config.region.command))?;
+        if args.is_empty() {
+            return Err(String::from("received empty region command"));
+        }
         let regex = Regex::new(r"^.+@-?\d+,-?\d+,\d+,\d+$").map_err(|err| format!("received invalid regex: {err}"))?;
 
         Ok(Self { config, regex, args })
     }
+
+    /// run the region command and block until the region is selected
+    ///
+    /// returns `None` when the selection was cancelled (the command didn't print anything)
+    pub fn select(&self) -> Result<Option<String>, String> {
+        run_region_command(&self.args, &self.regex)
+    }
+}
+
+/// run the region command and validate the selected region against the regex
+fn run_region_command(args: &[String], regex: &Regex) -> Result<Option<String>, String> {
+    let mut command = Command::new(&args[0]);
+    command.args(&args[1..]);
+    log::info!("using {command:?} as region command");
+    let output = command.output().map_err(|err| format!("error whilst selecting share region: {err}"))?;
+    let region = String::from_utf8_lossy(&output.stdout);
+    let region = region.trim();
+    if region.is_empty() {
+        return Ok(None);
+    }
+    if regex.is_match(region) {
+        Ok(Some(region.to_string()))
+    } else {
+        Err(format!("region command returned output '{region}': expected '<output>@<x>,<y>,<w>,<h>'"))
+    }
 }
 
 impl View for RegionView<'_> {
@@ -46,29 +74,29 @@ impl View for RegionView<'_> {
         let args = self.args.clone();
         button.connect_clicked(move |btn| {
             if let Some(root) = btn.root() {
-                let mut command = Command::new(&args[0]);
-                command.args(&args[1..]);
-                log::info!("using {command:?} as region command");
                 root.hide();
 
-                let region_regex = regex.clone();
+                let (regex, args) = (regex.clone(), args.clone());
                 glib::spawn_future_local(async move {
-                    match command.output() {
-                        Ok(output) => {
-                            let region = String::from_utf8_lossy(&output.stdout);
-                            let region = region.trim();
-                            if region_regex.is_match(region) {
-                                root.activate_action("win.select", Some(&format!("region:{region}").to_variant()))
-                                    .expect("select action should be registered on the window");
-                            } else {
-                                log::error!(
-                                    "region command returned output '{region}': expected '<output>@<x>,<y>,<w>,<h>'"
-                                );
-                                root.show();
-                            }
+                    // the command blocks until the region is selected, therefore it's run outside of the main thread
+                    let selection = match tokio::task::spawn_blocking(move || run_region_command(&args, &regex)).await {
+                        Ok(selection) => selection,
+                        Err(err) => {
+                            log::error!("unable to join region command task: {err}");
+                            return root.show();
+                        }
+                    };
+                    match selection {
+                        Ok(Some(region)) => {
+                            root.activate_action("win.select", Some(&format!("region:{region}").to_variant()))
+                                .expect("select action should be registered on the window");
+                        }
+                        Ok(None) => {
+                            log::info!("region selection was cancelled");
+                            root.show();
                         }
                         Err(err) => {
-                            log::error!("error whilst selecting share region: {err}");
+                            log::error!("{err}");
                             root.show();
                         }
                     }
@@ -80,6 +108,6 @@ impl View for RegionView<'_> {
     }
 
     fn label(&self) -> Label {
-        Label::builder().css_classes([self.config.classes.tab_label.as_str()]).label("Region").build()
+        tab_label(Page::Region, self.config)
     }
 }
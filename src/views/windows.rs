@@ -1,246 +1,722 @@
-use std::sync::Arc;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
 
-use glib::{clone, variant::ToVariant};
+use glib::{
+    Propagation, clone,
+    object::{Cast, ObjectExt},
+};
 use gtk4::{
-    Box, FlowBox, FlowBoxChild, GestureClick, Label, Picture, ScrolledWindow,
-    prelude::{BoxExt, EventControllerExt, FlowBoxChildExt, WidgetExt},
+    Box, EventControllerKey, EventControllerScroll, EventControllerScrollFlags, FlowBox, FlowBoxChild, GestureClick,
+    GestureZoom, Label, ListBox, ListBoxRow, Picture, ScrolledWindow, SelectionMode, Widget,
+    gdk::{Key, ModifierType},
+    prelude::{BoxExt, EventControllerExt, FlowBoxChildExt, GestureExt, WidgetExt},
 };
-use hyprland::{
-    data::{Client, Clients, Monitor, Monitors, Transforms},
-    shared::HyprData,
+use hyprland::data::{Client, Monitor, Transforms};
+use hyprland_preview_share_picker_lib::{
+    error::Error,
+    frame::{CaptureSource, FrameManager},
+    image::Image,
+    toplevel::{Toplevel, ToplevelHandle},
 };
-use hyprland_preview_share_picker_lib::{frame::FrameManager, image::Image, toplevel::Toplevel};
 use tokio::sync::oneshot::{Receiver, Sender};
-use wayland_client::Connection;
+use tracing::Instrument;
+
+use crate::{
+    config::{Config, Page, WindowLayout, WindowSort},
+    image::ImageExt,
+    reconnect::Reconnect,
+    stats::WindowStats,
+    util::{client_handle, display_title, inactive_workspace, match_client_with_overrides},
+};
 
-use crate::{config::Config, image::ImageExt, util::ClientExt};
+use super::{
+    ArmedClass, CaptureProgress, PageFilter, View, build_badge, connect_hover, matches_query, select, set_badge_text,
+    set_class_icon, set_placeholder, show_pixbuf, tab_label,
+};
 
-use super::View;
+/// bounds of the zoom factor applied to the widget size of the cards
+const MIN_ZOOM: f64 = 0.5;
+const MAX_ZOOM: f64 = 3.0;
+/// zoom factor change per scroll step
+const ZOOM_STEP: f64 = 0.1;
+/// maximum difference of the color channels of captures which are considered to have a single color
+const UNIFORM_TOLERANCE: u8 = 2;
+/// delay before a capture with a single color is captured again
+const UNIFORM_RECAPTURE_DELAY: Duration = Duration::from_millis(100);
+/// size in pixels of the checkerboard squares drawn below transparent window previews
+const CHECKERBOARD_SQUARE_SIZE: u32 = 8;
+/// height of the previews in the list layout relative to `image.widget_size`
+const LIST_PREVIEW_SCALE: f64 = 0.4;
+/// aspect ratio (width / height) of the previews in the list layout unless `windows.thumbnail_aspect` is set
+const LIST_PREVIEW_ASPECT: f64 = 16.0 / 9.0;
 
 pub struct WindowsView<'a> {
-    toplevels: &'a [Toplevel],
+    toplevels: Vec<&'a Toplevel>,
     config: &'a Config,
-    manager: Arc<FrameManager>,
-    clients: Vec<Client>,
-    monitors: Vec<Monitor>,
+    /// the frame manager is missing when the capture protocol isn't available and application icons are shown instead
+    manager: Option<Arc<FrameManager>>,
+    reconnect: Arc<Reconnect<FrameManager>>,
+    /// foreign toplevel handles of the toplevels by their id
+    handles: HashMap<u64, ToplevelHandle>,
+    clients: &'a [Client],
+    monitors: &'a [Monitor],
+    progress: CaptureProgress,
+    /// built cards with the title and class of their toplevel
+    cards: RefCell<Vec<(Widget, String, String)>>,
 }
 
 impl<'a> WindowsView<'a> {
-    pub fn new(connection: &'a Connection, toplevels: &'a [Toplevel], config: &'a Config) -> Result<Self, String> {
-        let manager = FrameManager::new(connection)
-            .map(Arc::new)
-            .map_err(|err| format!("unable to create new frame manager from connection: {err}"))?;
-        let clients = Clients::get()
-            .map(|clients| {
-                clients
-                    .into_iter()
-                    .map(|mut client| {
-                        client.sanitize();
-                        client
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .map_err(|err| format!("unable to get clients from hyprland socket: {err}"))?;
-        let monitors = Monitors::get()
-            .map(|monitors| monitors.into_iter().collect::<Vec<_>>())
-            .map_err(|err| format!("unable to get monitors from hyprland socket: {err}"))?;
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        manager: Option<Arc<FrameManager>>,
+        reconnect: Arc<Reconnect<FrameManager>>,
+        toplevels: &'a [Toplevel],
+        handles: HashMap<u64, ToplevelHandle>,
+        clients: &'a [Client],
+        monitors: &'a [Monitor],
+        progress: CaptureProgress,
+        config: &'a Config,
+    ) -> Self {
+        let mut toplevels: Vec<_> = toplevels.iter().filter(|toplevel| !Self::is_excluded(toplevel, config)).collect();
+        if config.windows.sort_by == WindowSort::Frequency {
+            let stats = WindowStats::load();
+            // the cards are prepended, therefore the most frequently selected windows are built last
+            toplevels.sort_by_key(|toplevel| stats.count(&toplevel.class));
+        }
+
+        Self { toplevels, config, manager, reconnect, handles, clients, monitors, progress, cards: RefCell::new(Vec::new()) }
+    }
+
+    /// check whether a toplevel should be hidden from the picker
+    fn is_excluded(toplevel: &Toplevel, config: &Config) -> bool {
+        if config.windows.exclude_classes.contains(&toplevel.class) {
+            log::debug!("excluding toplevel {} with class {}", toplevel.id, toplevel.class);
+            return true;
+        }
+        if config.windows.exclude_untitled && toplevel.class.is_empty() && toplevel.title.is_empty() {
+            log::debug!("excluding toplevel {} without class and title", toplevel.id);
+            return true;
+        }
+        false
+    }
+
+    /// scale the cards using ctrl+scroll or pinch gestures on the page
+    ///
+    /// only the size of the picture widgets is changed, gtk rescales the existing pixbufs without capturing new frames
+    fn connect_zoom(&self, scrolled_window: &ScrolledWindow, grid: Option<&FlowBox>, pictures: Vec<Picture>) {
+        let zoom = Rc::new(Cell::new(1.0));
+        let (width, height) = preview_size(self.config);
+        let min_per_row = self.config.windows.min_per_row;
+        let grid = grid.map(|grid| grid.downgrade());
+        let apply_zoom = Rc::new(clone!(
+            #[strong]
+            zoom,
+            move |value: f64| {
+                let value = value.clamp(MIN_ZOOM, MAX_ZOOM);
+                zoom.set(value);
+                // unset size requests of `-1` are kept
+                let scale = |size: i32| if size < 0 { size } else { (size as f64 * value) as i32 };
+                pictures.iter().for_each(|picture| picture.set_size_request(scale(width), scale(height)));
+                // allow fewer cards per row when zooming in such that the cards can grow in width as well
+                if let Some(grid) = grid.as_ref().and_then(|grid| grid.upgrade()) {
+                    grid.set_min_children_per_line(((min_per_row as f64 / value).round() as u32).max(1));
+                }
+            }
+        ));
 
-        Ok(Self { toplevels, config, manager, clients, monitors })
+        let scroll = EventControllerScroll::new(EventControllerScrollFlags::VERTICAL);
+        scroll.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        scroll.connect_scroll(clone!(
+            #[strong]
+            zoom,
+            #[strong]
+            apply_zoom,
+            move |controller, _, dy| {
+                if !controller.current_event_state().contains(ModifierType::CONTROL_MASK) {
+                    return Propagation::Proceed;
+                }
+                apply_zoom(zoom.get() - dy * ZOOM_STEP);
+                Propagation::Stop
+            }
+        ));
+        scrolled_window.add_controller(scroll);
+
+        let pinch = GestureZoom::new();
+        let pinch_start = Rc::new(Cell::new(1.0));
+        pinch.connect_begin(clone!(
+            #[strong]
+            pinch_start,
+            move |_, _| pinch_start.set(zoom.get())
+        ));
+        pinch.connect_scale_changed(move |_, scale| apply_zoom(pinch_start.get() * scale));
+        scrolled_window.add_controller(pinch);
     }
 }
 
 impl View for WindowsView<'_> {
     fn build(&self) -> ScrolledWindow {
-        let container = FlowBox::builder()
-            .vexpand(false)
-            .row_spacing(self.config.windows.spacing)
-            .column_spacing(self.config.windows.spacing)
-            .orientation(gtk4::Orientation::Horizontal)
-            .homogeneous(true)
-            .min_children_per_line(self.config.windows.min_per_row)
+        let container = CardContainer::new(self.config);
+        let scrolled_window = ScrolledWindow::builder()
+            .child(container.widget())
+            .css_classes([self.config.classes.notebook_page.as_str()])
             .build();
-        let scrolled_window =
-            ScrolledWindow::builder().child(&container).css_classes([self.config.classes.notebook_page.as_str()]).build();
 
         let mut cards = 0;
+        let mut pictures = Vec::new();
+        let mut badges = Vec::new();
         self.toplevels.iter().for_each(|toplevel| {
             log::debug!("attempting to capture frame for toplevel {}", toplevel.id);
-            // this method is kindof bad since multiple windows could have the same class and title but afaik there is no clean
-            // way to get a hyprland window address for a wayland toplevel id
             log::debug!("toplevel = {toplevel:?}");
-            let client = match self.clients.iter().find(|c| c.class.eq(&toplevel.class) && c.title.eq(&toplevel.title)) {
-                Some(client) => client,
-                None => return log::error!("unable to find hyprland client which matches toplevel class and title"),
-            };
-            let monitor = match self.monitors.iter().find(|m| m.id == client.monitor) {
-                Some(monitor) => monitor,
-                None => return log::error!("unable to find hyprland monitor for hyprland client"),
-            };
-
-            let handle_str = &format!("{}", client.address)[2..];
-            let handle = match u64::from_str_radix(handle_str, 16) {
-                Ok(handle) => handle,
-                Err(err) => return log::error!("unable to convert client address to u64: {err}"),
+            // the card is always built to keep the toplevel selectable, even without a matching hyprland client
+            let client_match = match_client_with_overrides(toplevel, self.clients, &self.config.windows.address_overrides);
+            if client_match.is_none() {
+                log::warn!("unable to find hyprland client which matches toplevel {}", toplevel.id);
+            }
+            let client = client_match.map(|client_match| client_match.client());
+            // windows on special workspaces have a negative workspace id
+            let unmapped = client.is_some_and(|client| !client.mapped || client.hidden || client.workspace.id < 0);
+            if unmapped && self.config.windows.only_mapped {
+                return log::debug!("skipping toplevel {}: window is not mapped or hidden", toplevel.id);
+            }
+            // the capture is requested anyway since switching to the workspace would change what the user sees
+            let workspace = client.and_then(|client| inactive_workspace(client, self.monitors)).map(String::from);
+            if let Some(workspace) = &workspace {
+                log::debug!("toplevel {} is on inactive workspace {workspace}", toplevel.id);
+            }
+            let transform = match client.and_then(|client| self.monitors.iter().find(|m| m.id == client.monitor)) {
+                Some(monitor) => monitor.transform,
+                None => {
+                    log::warn!("unable to find hyprland monitor for toplevel {}: using normal transform", toplevel.id);
+                    Transforms::Normal
+                }
             };
 
-            let window_card = WindowCard::new(toplevel, self.config, monitor.transform, handle, self.manager.clone());
-            let card = match window_card.build() {
-                Ok(card) => card,
+            // the toplevel window address is preferred anyway, therefore the card is still built without the alt handle
+            let handle = client.and_then(|client| match client_handle(client) {
+                Ok(handle) => Some(handle),
+                Err(err) => {
+                    log::warn!("unable to convert client address to u64: {err}");
+                    None
+                }
+            });
+
+            let window_card = WindowCard::new(
+                toplevel,
+                self.config,
+                transform,
+                self.handles.get(&toplevel.id).cloned(),
+                handle,
+                client_match.is_some_and(|client_match| client_match.is_ambiguous()),
+                unmapped,
+                workspace,
+                // stagger the captures to avoid overloading the compositor
+                Duration::from_millis(self.config.image.capture_stagger_ms * cards as u64),
+                self.manager.clone(),
+                self.reconnect.clone(),
+            );
+            let card = match window_card.build(&self.progress) {
+                Ok((card, picture, badge)) => {
+                    pictures.push(picture);
+                    badges.extend(badge);
+                    card
+                }
                 Err(err) => return log::error!("unable to build window card for toplevel {}: {err}", toplevel.id),
             };
 
             cards += 1;
-            container.insert(&card, 0);
+            container.prepend(&card);
+            self.cards.borrow_mut().push((card, toplevel.title.clone(), toplevel.class.clone()));
         });
 
+        // the cards are prepended, therefore the last built card is the first one of the page
+        badges.iter().enumerate().for_each(|(index, badge)| set_badge_text(badge, &(cards as usize - index).to_string()));
+
         // if there are less cards than max, spread them evenly on a single row
-        container.set_max_children_per_line(self.config.windows.max_per_row.min(cards));
+        if let CardContainer::Grid(grid) = &container {
+            grid.set_max_children_per_line(self.config.windows.max_per_row.min(cards));
+        }
+        self.connect_zoom(&scrolled_window, container.grid(), pictures);
 
         scrolled_window
     }
 
     fn label(&self) -> Label {
-        Label::builder().css_classes([self.config.classes.tab_label.as_str()]).label("Windows").build()
+        tab_label(Page::Windows, self.config)
+    }
+
+    fn filter(&self) -> Option<PageFilter> {
+        let cards = self.cards.borrow().clone();
+        Some(std::boxed::Box::new(move |query| {
+            cards.iter().fold(false, |matched, (card, title, class)| {
+                let visible = matches_query(&[title, class], query);
+                card.set_visible(visible);
+                matched || visible
+            })
+        }))
+    }
+}
+
+/// Container of the window cards in the configured layout
+enum CardContainer {
+    Grid(FlowBox),
+    List(ListBox),
+}
+
+impl CardContainer {
+    fn new(config: &Config) -> Self {
+        match config.windows.layout {
+            WindowLayout::Grid => Self::Grid(
+                FlowBox::builder()
+                    .vexpand(false)
+                    .row_spacing(config.windows.spacing)
+                    .column_spacing(config.windows.spacing)
+                    .orientation(gtk4::Orientation::Horizontal)
+                    .homogeneous(true)
+                    .min_children_per_line(config.windows.min_per_row)
+                    .build(),
+            ),
+            // the rows are selected through the action like the grid cards, therefore the list keeps no selection
+            WindowLayout::List => Self::List(ListBox::builder().vexpand(false).selection_mode(SelectionMode::None).build()),
+        }
+    }
+
+    fn widget(&self) -> &Widget {
+        match self {
+            CardContainer::Grid(grid) => grid.upcast_ref(),
+            CardContainer::List(list) => list.upcast_ref(),
+        }
+    }
+
+    fn grid(&self) -> Option<&FlowBox> {
+        match self {
+            CardContainer::Grid(grid) => Some(grid),
+            CardContainer::List(_) => None,
+        }
+    }
+
+    /// insert the card in front of all other cards
+    fn prepend(&self, card: &Widget) {
+        match self {
+            CardContainer::Grid(grid) => grid.insert(card, 0),
+            CardContainer::List(list) => list.prepend(card),
+        }
+    }
+}
+
+/// get the size request of the previews
+///
+/// the previews of the grid only request a height whereas the list previews request a fixed width as well such that
+/// the titles of all rows are aligned
+fn preview_size(config: &Config) -> (i32, i32) {
+    match config.windows.layout {
+        WindowLayout::Grid => (-1, config.image.widget_size),
+        WindowLayout::List => {
+            let height = config.image.widget_size as f64 * LIST_PREVIEW_SCALE;
+            let aspect = config.windows.thumbnail_aspect.unwrap_or(LIST_PREVIEW_ASPECT);
+            ((height * aspect) as i32, height as i32)
+        }
     }
 }
 
 struct WindowCard<'a> {
     toplevel: &'a Toplevel,
     config: &'a Config,
-    manager: Arc<FrameManager>,
+    manager: Option<Arc<FrameManager>>,
+    reconnect: Arc<Reconnect<FrameManager>>,
     transform: Transforms,
-    alt_handle: u64,
+    toplevel_handle: Option<ToplevelHandle>,
+    alt_handle: Option<u64>,
+    ambiguous: bool,
+    unmapped: bool,
+    /// name of the workspace of the window when it isn't shown on any monitor
+    workspace: Option<String>,
+    capture_delay: Duration,
 }
 
 impl<'a> WindowCard<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         toplevel: &'a Toplevel,
         config: &'a Config,
         transform: Transforms,
-        alt_handle: u64,
-        manager: Arc<FrameManager>,
+        toplevel_handle: Option<ToplevelHandle>,
+        alt_handle: Option<u64>,
+        ambiguous: bool,
+        unmapped: bool,
+        workspace: Option<String>,
+        capture_delay: Duration,
+        manager: Option<Arc<FrameManager>>,
+        reconnect: Arc<Reconnect<FrameManager>>,
     ) -> Self {
-        WindowCard { alt_handle, toplevel, config, manager, transform }
+        WindowCard {
+            toplevel_handle,
+            alt_handle,
+            ambiguous,
+            unmapped,
+            workspace,
+            capture_delay,
+            toplevel,
+            config,
+            manager,
+            reconnect,
+            transform,
+        }
     }
 
-    pub fn build(self) -> Result<FlowBoxChild, String> {
+    /// build the card and request its frame
+    ///
+    /// the returned badge is only built when enabled and its text is expected to be set by the caller
+    pub fn build(self, progress: &CaptureProgress) -> Result<(Widget, Picture, Option<Label>), String> {
         let (tx, rx) = tokio::sync::oneshot::channel();
         let picture = self.build_picture();
-        let card = self.build_card(&picture);
+        let (card, badge) = match self.config.windows.layout {
+            WindowLayout::Grid => self.build_card(&picture),
+            WindowLayout::List => self.build_row(&picture),
+        };
         let container = self.build_card_container(&card);
 
         self.request_frame(tx);
-        self.update_frame_lazily(card.clone(), picture.clone(), rx);
+        self.update_frame_lazily(card.clone(), picture.clone(), rx, progress.clone());
 
-        Ok(container)
+        Ok((container, picture, badge))
     }
 
     fn build_picture(&self) -> Picture {
+        let (width, height) = preview_size(self.config);
         Picture::builder()
             .vexpand(true)
             .valign(gtk4::Align::Center)
-            .height_request(self.config.image.widget_size)
+            .width_request(width)
+            .height_request(height)
             .content_fit(gtk4::ContentFit::Contain)
             .css_classes([self.config.classes.image.as_str()])
             .build()
     }
 
-    fn build_card(&self, picture: &Picture) -> Box {
+    /// build the box of the card with the classes describing the state of the toplevel
+    fn build_card_box(&self, orientation: gtk4::Orientation) -> Box {
         let container = Box::builder()
-            .orientation(gtk4::Orientation::Vertical)
+            .orientation(orientation)
             .vexpand(false)
             .hexpand(false)
             .halign(gtk4::Align::Fill)
             .valign(gtk4::Align::Start)
             .css_classes([self.config.classes.image_card.as_str(), self.config.classes.image_card_loading.as_str()])
             .build();
+        if self.ambiguous {
+            container.add_css_class(&self.config.classes.image_card_ambiguous);
+        }
+        if self.unmapped {
+            container.add_css_class(&self.config.classes.image_card_unmapped);
+        }
+        if self.workspace.is_some() {
+            container.add_css_class(&self.config.classes.image_card_inactive_workspace);
+        }
+        container
+    }
+
+    /// build the preview, optionally wrapped in an overlay with the badge
+    fn build_preview(&self, picture: &Picture) -> (Widget, Option<Label>) {
+        if self.config.image.badge {
+            let (overlay, badge) = build_badge(picture, &self.config.classes.image_badge);
+            (overlay.upcast(), Some(badge))
+        } else {
+            (picture.clone().upcast(), None)
+        }
+    }
+
+    fn build_card(&self, picture: &Picture) -> (Box, Option<Label>) {
+        let container = self.build_card_box(gtk4::Orientation::Vertical);
 
         let label = Label::builder()
             .max_width_chars(1)
-            .label(self.toplevel.title.as_str())
+            .label(display_title(self.toplevel, self.config.windows.untitled_label.as_deref()))
             .ellipsize(gtk4::pango::EllipsizeMode::End)
             .single_line_mode(true)
             .css_classes([self.config.classes.image_label.as_str()])
             .hexpand(false)
             .build();
 
-        container.append(picture);
+        let (preview, badge) = self.build_preview(picture);
+        container.append(&preview);
         container.append(&label);
-        container
+        (container, badge)
     }
 
-    fn build_card_container(&self, card: &Box) -> FlowBoxChild {
-        let container = FlowBoxChild::builder().halign(gtk4::Align::Fill).valign(gtk4::Align::Fill).child(card).build();
+    /// build the card of the list layout with the preview, the title and the class next to each other
+    fn build_row(&self, picture: &Picture) -> (Box, Option<Label>) {
+        let container = self.build_card_box(gtk4::Orientation::Horizontal);
+        container.add_css_class(&self.config.classes.image_card_row);
+        container.set_spacing(self.config.windows.spacing as i32);
+
+        let title = Label::builder()
+            .label(display_title(self.toplevel, self.config.windows.untitled_label.as_deref()))
+            .ellipsize(gtk4::pango::EllipsizeMode::End)
+            .single_line_mode(true)
+            .xalign(0.0)
+            .css_classes([self.config.classes.image_label.as_str()])
+            .hexpand(true)
+            .build();
+        let class = Label::builder()
+            .label(&self.toplevel.class)
+            .ellipsize(gtk4::pango::EllipsizeMode::End)
+            .single_line_mode(true)
+            .xalign(1.0)
+            .css_classes([self.config.classes.image_sublabel.as_str()])
+            .build();
+
+        let (preview, badge) = self.build_preview(picture);
+        container.append(&preview);
+        container.append(&title);
+        container.append(&class);
+        (container, badge)
+    }
+
+    /// wrap the card into the child widget of the layout and connect the selection
+    fn build_card_container(&self, card: &Box) -> Widget {
+        let id = self.toplevel.id;
+        let selected_class = self.config.classes.image_card_selected.clone();
+        let title = display_title(self.toplevel, self.config.windows.untitled_label.as_deref());
+        let tooltip = format!("{title}\n{}", self.toplevel.class);
+        let container: Widget = match self.config.windows.layout {
+            WindowLayout::Grid => {
+                let child = FlowBoxChild::builder()
+                    .halign(gtk4::Align::Fill)
+                    .valign(gtk4::Align::Fill)
+                    .tooltip_text(tooltip)
+                    .child(card)
+                    .build();
+                child.connect_activate(clone!(
+                    #[weak]
+                    card,
+                    #[strong]
+                    selected_class,
+                    move |child| select(child, &card, &format!("window:{id}"), &selected_class)
+                ));
+                child.upcast()
+            }
+            WindowLayout::List => {
+                let row = ListBoxRow::builder().tooltip_text(tooltip).child(card).build();
+                // the list box only activates rows on its own signal, therefore the keys are handled on the row itself
+                let keys = EventControllerKey::new();
+                keys.connect_key_pressed(clone!(
+                    #[weak]
+                    card,
+                    #[strong]
+                    selected_class,
+                    #[upgrade_or]
+                    Propagation::Proceed,
+                    move |controller, key, _, _| {
+                        if !matches!(key, Key::Return | Key::KP_Enter | Key::space) {
+                            return Propagation::Proceed;
+                        }
+                        if let Some(row) = controller.widget() {
+                            select(&row, &card, &format!("window:{id}"), &selected_class);
+                        }
+                        Propagation::Stop
+                    }
+                ));
+                row.add_controller(keys);
+                row.upcast()
+            }
+        };
 
         let gesture = GestureClick::new();
+        // listen to all buttons and filter in the handler to ignore clicks with other buttons
+        gesture.set_button(0);
         let clicks = self.config.windows.clicks;
-        let id = self.toplevel.id;
-        gesture.connect_released(move |gesture, n, _, _| {
-            if n as i64 == clicks as i64
-                && let Some(widget) = gesture.widget()
-            {
-                widget
-                    .activate_action("win.select", Some(&format!("window:{id}").to_variant()))
-                    .expect("select action should be registered on the window")
+        let select_button = self.config.windows.select_button.number();
+        let armed = ArmedClass::new(&self.config.classes.image_card_armed);
+        let hover_class = self.config.classes.image_card_hover.clone();
+        gesture.connect_released(clone!(
+            #[weak]
+            card,
+            #[strong]
+            selected_class,
+            move |gesture, n, _, _| {
+                if gesture.current_button() != select_button {
+                    return;
+                }
+                if (n as i64) < clicks as i64 {
+                    armed.arm(card.upcast_ref());
+                } else if n as i64 == clicks as i64
+                    && let Some(widget) = gesture.widget()
+                {
+                    armed.disarm(card.upcast_ref());
+                    card.remove_css_class(&hover_class);
+                    select(&widget, &card, &format!("window:{id}"), &selected_class);
+                }
             }
-        });
+        ));
         container.add_controller(gesture);
-        container.connect_activate(move |child| {
-            child
-                .activate_action("win.select", Some(&format!("window:{id}").to_variant()))
-                .expect("select action should be registered on the window")
-        });
+        connect_hover(&container, card, &self.config.classes.image_card_hover);
         container
     }
 
-    fn request_frame(&self, tx: Sender<Image>) {
-        let handle = self.toplevel.window_address.unwrap_or_else(|| {
-            log::warn!(
-                "missing window address in toplevel {}: falling back to potentially non unique socket window address",
-                self.toplevel.id
-            );
-            self.alt_handle
-        });
+    fn request_frame(&self, tx: Sender<Result<Image, Error>>) {
+        let Some(manager) = &self.manager else {
+            // dropping the sender shows the fallback of the card
+            return log::debug!("unable to capture frame for toplevel {}: no frame manager available", self.toplevel.id);
+        };
+        let source = match (self.toplevel.window_address, &self.toplevel_handle, self.alt_handle) {
+            (Some(handle), _, _) => CaptureSource::Address(handle),
+            (None, Some(toplevel_handle), _) => {
+                log::debug!("missing window address in toplevel {}: capturing by foreign toplevel handle", self.toplevel.id);
+                CaptureSource::Toplevel(toplevel_handle.clone())
+            }
+            (None, None, Some(alt_handle)) => {
+                log::warn!(
+                    "missing window address in toplevel {}: falling back to potentially non unique socket window address",
+                    self.toplevel.id
+                );
+                CaptureSource::Address(alt_handle)
+            }
+            (None, None, None) => {
+                // dropping the sender leaves the card without a preview but keeps it selectable
+                return log::error!(
+                    "unable to capture frame for toplevel {}: no window address available",
+                    self.toplevel.id
+                );
+            }
+        };
         let id = self.toplevel.id;
-        let resize_size = self.config.image.resize_size;
-        let manager = self.manager.clone();
+        let resize_size = self.config.windows.resize_size.unwrap_or(self.config.image.resize_size);
         let transform = self.transform;
+        let flip = self.config.image.flip;
+        let inset = self.config.windows.decoration_inset;
+        let upscale = self.config.image.upscale;
+        let show_transparency = self.config.image.show_transparency;
+        let thumbnail_aspect = self.config.windows.thumbnail_aspect;
+        // foreign toplevel handles belong to the lost connection, therefore only window addresses are captured again
+        let retry = match &source {
+            CaptureSource::Address(_) => Some(source.clone()),
+            CaptureSource::Toplevel(_) => None,
+        };
+        let reconnect = self.reconnect.clone();
+        let recapture = (manager.clone(), source.clone());
+        let capture = manager.capture_source_image(source);
+        let capture_delay = self.capture_delay;
+        let span = tracing::debug_span!("capture", toplevel = id);
+
+        let task = async move {
+            if !capture_delay.is_zero() {
+                tokio::time::sleep(capture_delay).await;
+            }
+            let result = match (capture.await, retry) {
+                (Err(err), Some(source)) if err.is_connection_lost() => match reconnect.manager() {
+                    Ok(manager) => manager.capture_source_image(source).await,
+                    Err(reconnect_err) => {
+                        log::error!("{reconnect_err}");
+                        Err(err)
+                    }
+                },
+                (result, _) => result,
+            };
+            // windows which weren't rendered yet are captured as a single color, therefore they're captured once more
+            let result = match result {
+                Ok(img) if img.is_uniform(UNIFORM_TOLERANCE) => {
+                    log::debug!("capture of toplevel {id} has a single color: capturing again");
+                    tokio::time::sleep(UNIFORM_RECAPTURE_DELAY).await;
+                    let (manager, source) = recapture;
+                    match manager.capture_source_image(source).await {
+                        Ok(recaptured) => Ok(recaptured),
+                        Err(err) => {
+                            log::warn!("unable to capture toplevel {id} again: {err}");
+                            Ok(img)
+                        }
+                    }
+                }
+                result => result,
+            };
+            let mut img = match result {
+                // the alpha channel is needed until the image is composited onto the checkerboard
+                Ok(img) if show_transparency => img,
+                Ok(img) => match img.into_rgb() {
+                    Ok(img) => img,
+                    Err(err) => return log::error!("unable to convert Xrgb image to rgb: {err}"),
+                },
+                Err(err) => {
+                    log::error!("unable to capture frame for toplevel {id}: {err}");
+                    let _ = tx.send(Err(err));
+                    return;
+                }
+            };
 
-        tokio::spawn(clone!(
-            #[to_owned]
-            manager,
-            async move {
-                let buffer = match manager.to_owned().capture_frame(handle) {
-                    Ok(buffer) => buffer,
-                    Err(err) => return log::error!("unable to capture frame for toplevel {id}: {err}"),
-                };
-                let mut img = match Image::new(buffer) {
-                    Ok(img) => match img.into_rgb() {
-                        Ok(img) => img,
-                        Err(err) => return log::error!("unable to convert Xrgb image to rgb: {err}"),
-                    },
-                    Err(err) => return log::error!("unable to create image from buffer: {err}"),
-                };
-
+            img.apply_inset(inset);
+            // the image is transformed before it's resized such that the resize filter smooths the final orientation
+            img = img.transform(transform.into());
+            // the aspect ratio is applied after the transformation such that it matches the orientation of the card
+            if let Some(aspect) = thumbnail_aspect {
+                img.crop_to_aspect(aspect);
+            }
+            if upscale {
+                img.resize_to_fill(resize_size);
+            } else {
                 img.resize_to_fit(resize_size);
-                img = img.transform(transform.into());
-
-                if tx.send(img).is_err() {
-                    log::error!("unable to transmit image for toplevel {id}: channel is closed");
-                };
-                log::debug!("transmitted image for toplevel {id}");
             }
-        ));
+            img.apply_flip(flip);
+            if show_transparency {
+                img.composite_checkerboard(CHECKERBOARD_SQUARE_SIZE);
+            }
+
+            if tx.send(Ok(img)).is_err() {
+                log::error!("unable to transmit image for toplevel {id}: channel is closed");
+            };
+            log::debug!("transmitted image for toplevel {id}");
+        };
+        tokio::spawn(task.instrument(span));
     }
 
-    fn update_frame_lazily(&self, card: Box, picture: Picture, rx: Receiver<Image>) {
+    fn update_frame_lazily(
+        &self,
+        card: Box,
+        picture: Picture,
+        rx: Receiver<Result<Image, Error>>,
+        progress: CaptureProgress,
+    ) {
         let id = self.toplevel.id;
         let loading_class = self.config.classes.image_card_loading.clone();
-        glib::spawn_future_local(async move {
+        let render_interpolation = self.config.image.render_interpolation;
+        let placeholder = self.config.image.placeholder_path.as_ref().map(|path| self.config.resolve_path(path));
+        let icon_class = self.config.windows.icon_fallback.then(|| self.toplevel.class.clone());
+        let workspace = self.workspace.clone();
+        let sublabel_class = self.config.classes.image_sublabel.clone();
+        // like the title the label mustn't widen the cards of the grid
+        let max_width_chars = if self.config.windows.layout == WindowLayout::Grid { 1 } else { -1 };
+        let set_fallback = move |picture: &Picture| match &icon_class {
+            Some(class) => set_class_icon(picture, class, placeholder.as_deref()),
+            None => set_placeholder(picture, placeholder.as_deref()),
+        };
+        let span = tracing::debug_span!("update", toplevel = id);
+        let task = async move {
             let img = match rx.await {
-                Ok(img) => img,
+                Ok(Ok(img)) => img,
+                Ok(Err(err)) => {
+                    // the error identifies the failed capture without having to look into the logs
+                    set_fallback(&picture);
+                    picture.set_tooltip_text(Some(&err.to_string()));
+                    // the compositor may not have a frame of windows on hidden workspaces, which is shown on the card
+                    if let Some(workspace) = &workspace {
+                        let label = Label::builder()
+                            .label(format!("on workspace {workspace} (no live preview)"))
+                            .max_width_chars(max_width_chars)
+                            .ellipsize(gtk4::pango::EllipsizeMode::End)
+                            .single_line_mode(true)
+                            .css_classes([sublabel_class.as_str()])
+                            .build();
+                        card.append(&label);
+                    }
+                    card.remove_css_class(&loading_class);
+                    return;
+                }
                 Err(err) => {
                     log::error!("unable to receive image for toplevel {id}: {err}");
+                    set_fallback(&picture);
                     card.remove_css_class(&loading_class);
                     return;
                 }
@@ -248,11 +724,24 @@ impl<'a> WindowCard<'a> {
 
             let pixbuf = match img.into_pixbuf() {
                 Ok(pixbuf) => pixbuf,
-                Err(err) => return log::error!("unable to create pixbuf for toplevel {id} image: {err}"),
+                Err(err) => {
+                    log::error!("unable to create pixbuf for toplevel {id} image: {err}");
+                    set_fallback(&picture);
+                    card.remove_css_class(&loading_class);
+                    return;
+                }
             };
 
-            picture.set_pixbuf(Some(&pixbuf));
+            show_pixbuf(&picture, pixbuf, render_interpolation);
             card.remove_css_class(&loading_class);
-        });
+        };
+        progress.add();
+        glib::spawn_future_local(
+            async move {
+                task.await;
+                progress.finish();
+            }
+            .instrument(span),
+        );
     }
 }